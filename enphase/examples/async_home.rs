@@ -0,0 +1,33 @@
+//! Fetches an Envoy's home.json and inverters at the same time, on a tokio runtime:
+//!
+//!     cargo run -p enphase --features async --example async_home -- https://envoy.local <token>
+
+use enphase::{
+    nonblocking::{get_home, get_inverters},
+    EnphaseError,
+};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), EnphaseError> {
+    let mut args = std::env::args().skip(1);
+    let (Some(url), Some(token)) = (args.next(), args.next()) else {
+        eprintln!("usage: async_home <url> <token>");
+        std::process::exit(2);
+    };
+    // the Envoy's certificate is self-signed
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+    let authorization = format!("Bearer {}", token);
+    let (home, inverters) = tokio::try_join!(
+        get_home(&client, &url, &authorization),
+        get_inverters(&client, &url, &authorization),
+    )?;
+    println!(
+        "{} devices talking to the Envoy, {} inverters producing {} W",
+        home.comm.num,
+        inverters.len(),
+        inverters.iter().map(|i| i.last_report_watts).sum::<i32>()
+    );
+    Ok(())
+}
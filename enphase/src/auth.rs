@@ -0,0 +1,121 @@
+use http_auth::PasswordClient;
+#[cfg(feature = "sync")]
+use serde::Deserialize;
+use std::convert::TryFrom as _;
+#[cfg(feature = "sync")]
+use tracing::{debug, info};
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+use crate::EnphaseError;
+
+/// Negotiates digest auth with the Envoy and returns the `Authorization` header value to send
+/// with every subsequent request. `agent` must not already be adding an `Authorization` header.
+#[cfg(feature = "sync")]
+#[tracing::instrument(level = "debug", skip(agent, password))]
+pub fn get_auth_header(
+    agent: &Agent,
+    url: &str,
+    username: &str,
+    password: &str,
+) -> Result<String, EnphaseError> {
+    let setup_url = format!("{}/installer/setup/home", url);
+    info!("negotiating digest auth with {}", url);
+    let auth_response = match agent.get(&setup_url).call() {
+        Err(ureq::Error::Status(401, response)) => response,
+        Err(e) => return Err(e.into()),
+        Ok(response) => {
+            return Err(EnphaseError::Auth(format!(
+                "expected a 401 challenge from {} but got status {}",
+                setup_url,
+                response.status()
+            )))
+        }
+    };
+    let response_header = auth_response.header("WWW-Authenticate").ok_or_else(|| {
+        EnphaseError::Auth(format!(
+            "{} did not send a WWW-Authenticate header",
+            setup_url
+        ))
+    })?;
+    debug!("{} challenged with {}", setup_url, response_header);
+    answer_challenge(response_header, username, password)
+}
+
+/// The `Authorization` header that answers the Envoy's digest `challenge`.
+pub(crate) fn answer_challenge(
+    challenge: &str,
+    username: &str,
+    password: &str,
+) -> Result<String, EnphaseError> {
+    let mut password_client = PasswordClient::try_from(challenge).map_err(EnphaseError::Auth)?;
+    password_client
+        .respond(&http_auth::PasswordParams {
+            username,
+            password,
+            uri: "*",
+            method: "GET",
+            body: Some(&[]),
+        })
+        .map_err(EnphaseError::Auth)
+}
+
+#[cfg(feature = "sync")]
+const ENLIGHTEN_LOGIN_URL: &str = "https://enlighten.enphaseenergy.com/login/login.json";
+#[cfg(feature = "sync")]
+const ENTREZ_TOKENS_URL: &str = "https://entrez.enphaseenergy.com/tokens";
+
+#[cfg(feature = "sync")]
+#[derive(Deserialize)]
+struct EnlightenSession {
+    session_id: String,
+}
+
+/// Gets a JWT for the Envoy with serial number `serial_number` from the Enphase cloud, using the
+/// owner's Enlighten login: log in to Enlighten for a session, then trade the session for a token
+/// scoped to that gateway. Firmware D7 and later (including IQ Gateways) no longer do digest auth
+/// and require `Authorization: Bearer <token>` instead.
+///
+/// Owner tokens are good for months, so fetch one at startup and reuse it rather than calling this
+/// for every request. `agent` should have timeouts, and trust the usual certificate authorities.
+#[cfg(feature = "sync")]
+pub fn fetch_enlighten_token(
+    agent: &Agent,
+    username: &str,
+    password: &str,
+    serial_number: &str,
+) -> Result<String, EnphaseError> {
+    info!(
+        "logging in to Enlighten for a token for Envoy {}",
+        serial_number
+    );
+    let login = match agent
+        .post(ENLIGHTEN_LOGIN_URL)
+        .send_form(&[("user[email]", username), ("user[password]", password)])
+    {
+        Err(ureq::Error::Status(401, _)) => {
+            return Err(EnphaseError::Auth(
+                "Enlighten rejected the username or password".to_string(),
+            ))
+        }
+        result => result?.into_string()?,
+    };
+    let session: EnlightenSession =
+        serde_json::from_str(&login).map_err(|source| EnphaseError::Deserialize {
+            url: ENLIGHTEN_LOGIN_URL.to_string(),
+            source,
+        })?;
+    debug!(
+        "trading the Enlighten session for a token at {}",
+        ENTREZ_TOKENS_URL
+    );
+    let token = agent
+        .post(ENTREZ_TOKENS_URL)
+        .send_json(serde_json::json!({
+            "session_id": session.session_id,
+            "serial_num": serial_number,
+            "username": username,
+        }))?
+        .into_string()?;
+    Ok(token.trim().to_string())
+}
@@ -0,0 +1,294 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use tracing::info;
+use ureq::{Agent, AgentBuilder, MiddlewareNext, Request, Response};
+
+use crate::{
+    clock::{get_date_time_display, DateTimeDisplayResponse},
+    consumption::{get_consumption_summary, ConsumptionSummaryResponse},
+    ensemble::{
+        get_dry_contacts, get_ensemble_inventory, get_ensemble_power, get_ensemble_status,
+        get_generator, get_generator_config, get_grid_relay, BatteryPower, DryContact,
+        EnsembleInventoryResponse, EnsembleStatusResponse, GeneratorConfig, GeneratorResponse,
+        GridRelayResponse,
+    },
+    ev_charger::{get_ev_charger, EvChargerResponse},
+    events::{get_events, Event},
+    grid_profile::{get_grid_profile, GridProfileResponse},
+    home::{get_home, HomeResponse},
+    info::{get_info, InfoResponse},
+    inventory::{get_inventory, InventoryResponse},
+    inverters::{get_inverters, InvertersResponse},
+    livedata::{enable_livedata_stream, get_livedata, LiveDataResponse},
+    meters::{
+        get_consumption_reports, get_ct_config, get_meter_config, get_meter_readings, get_meters,
+        ConsumptionReportResponse, CtConfigResponse, MeterConfigResponse, MeterReadingResponse,
+        MetersResponse,
+    },
+    network_display::{get_network_display, NetworkDisplayResponse},
+    newscan::{get_new_scan, NewScanResponse},
+    pdm::{get_device_data, get_inverter_energy, InverterEnergy, PdmDevice},
+    production::{
+        get_production, get_production_summary, ProductionResponse, ProductionSummaryResponse,
+    },
+    pvlimit::{get_pv_limit, PvLimitResponse},
+    retry::retry,
+    stream::{stream_meter, MeterStream},
+    tariff::{get_tariff, TariffResponse},
+    wireless::{get_wireless_display, WirelessDisplayResponse},
+    EnphaseError,
+};
+
+/// An authenticated connection to one Envoy. Each method fetches one endpoint; see the function
+/// it wraps for what happens when the Envoy doesn't have that endpoint.
+pub struct EnvoyClient {
+    agent: Agent,
+    url: String,
+    max_attempts: u32,
+    retry_base_delay: Duration,
+    /// the header the agent's middleware sends, when it came from [`EnvoyClient::with_authorization`]
+    authorization: Option<Arc<RwLock<String>>>,
+    reauthenticate: Option<Reauthenticate>,
+}
+
+type Reauthenticate = Box<dyn Fn() -> Result<String, EnphaseError> + Send + Sync>;
+
+impl EnvoyClient {
+    /// Uses `agent` as is, so it must already be adding the `Authorization` header.
+    pub fn new(agent: Agent, url: &str) -> EnvoyClient {
+        EnvoyClient {
+            agent,
+            url: url.trim_end_matches('/').to_string(),
+            max_attempts: 1,
+            retry_base_delay: Duration::ZERO,
+            authorization: None,
+            reauthenticate: None,
+        }
+    }
+
+    /// Retries requests that fail in a way that might be temporary up to `max_retries` times,
+    /// backing off from `base_delay`; see [`retry`]. Requests aren't retried by default.
+    pub fn with_retries(mut self, max_retries: u32, base_delay: Duration) -> EnvoyClient {
+        self.max_attempts = max_retries.saturating_add(1);
+        self.retry_base_delay = base_delay;
+        self
+    }
+
+    /// Builds an agent from `builder` that sends `authorization` with every request: the value
+    /// from [`crate::auth::get_auth_header`], or `Bearer <token>`.
+    pub fn with_authorization(
+        builder: AgentBuilder,
+        url: &str,
+        authorization: String,
+    ) -> EnvoyClient {
+        let authorization = Arc::new(RwLock::new(authorization));
+        let header = authorization.clone();
+        #[allow(clippy::result_large_err)] // the signature is dictated by ureq's middleware trait
+        let auth = move |req: Request, next: MiddlewareNext| -> Result<Response, ureq::Error> {
+            let req = req.set("Authorization", &header.read().unwrap());
+            next.handle(req)
+        };
+        EnvoyClient {
+            authorization: Some(authorization),
+            ..EnvoyClient::new(builder.middleware(auth).build(), url)
+        }
+    }
+
+    /// When a request is answered with a 401, calls `reauthenticate` for a new `Authorization`
+    /// header and tries the request once more. Digest nonces and tokens both expire, so a client
+    /// that runs for a long time needs this. Only has an effect on a client made with
+    /// [`EnvoyClient::with_authorization`].
+    pub fn with_reauthentication(
+        mut self,
+        reauthenticate: impl Fn() -> Result<String, EnphaseError> + Send + Sync + 'static,
+    ) -> EnvoyClient {
+        self.reauthenticate = Some(Box::new(reauthenticate));
+        self
+    }
+
+    fn fetch<T>(
+        &self,
+        get: impl Fn(&Agent, &str) -> Result<T, EnphaseError>,
+    ) -> Result<T, EnphaseError> {
+        let result = retry(
+            || get(&self.agent, &self.url),
+            self.max_attempts,
+            self.retry_base_delay,
+        );
+        let (Some(authorization), Some(reauthenticate)) =
+            (&self.authorization, &self.reauthenticate)
+        else {
+            return result;
+        };
+        match result {
+            Err(e) if e.status() == Some(401) => {
+                info!("{}; re-authenticating", e);
+                let header = retry(reauthenticate, self.max_attempts, self.retry_base_delay)?;
+                *authorization.write().unwrap() = header;
+                retry(
+                    || get(&self.agent, &self.url),
+                    self.max_attempts,
+                    self.retry_base_delay,
+                )
+                .map_err(|e| match e.status() {
+                    Some(401) => EnphaseError::Auth(format!(
+                        "{} rejected the new credentials after re-authenticating",
+                        self.url
+                    )),
+                    _ => e,
+                })
+            }
+            result => result,
+        }
+    }
+
+    pub fn agent(&self) -> &Agent {
+        &self.agent
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn info(&self) -> Result<Option<InfoResponse>, EnphaseError> {
+        self.fetch(get_info)
+    }
+
+    pub fn home(&self) -> Result<HomeResponse, EnphaseError> {
+        self.fetch(get_home)
+    }
+
+    pub fn inverters(&self) -> Result<Vec<InvertersResponse>, EnphaseError> {
+        self.fetch(get_inverters)
+    }
+
+    pub fn production(&self) -> Result<ProductionResponse, EnphaseError> {
+        self.fetch(get_production)
+    }
+
+    pub fn production_summary(&self) -> Result<ProductionSummaryResponse, EnphaseError> {
+        self.fetch(get_production_summary)
+    }
+
+    pub fn consumption_summary(&self) -> Result<Option<ConsumptionSummaryResponse>, EnphaseError> {
+        self.fetch(get_consumption_summary)
+    }
+
+    pub fn meter_config(&self) -> Result<Vec<MeterConfigResponse>, EnphaseError> {
+        self.fetch(get_meter_config)
+    }
+
+    pub fn meter_readings(&self) -> Result<Vec<MeterReadingResponse>, EnphaseError> {
+        self.fetch(get_meter_readings)
+    }
+
+    pub fn meters(&self) -> Result<Option<MetersResponse>, EnphaseError> {
+        self.fetch(get_meters)
+    }
+
+    pub fn consumption_reports(&self) -> Result<Vec<ConsumptionReportResponse>, EnphaseError> {
+        self.fetch(get_consumption_reports)
+    }
+
+    pub fn inventory(&self) -> Result<Vec<InventoryResponse>, EnphaseError> {
+        self.fetch(get_inventory)
+    }
+
+    pub fn ensemble_inventory(&self) -> Result<Vec<EnsembleInventoryResponse>, EnphaseError> {
+        self.fetch(get_ensemble_inventory)
+    }
+
+    pub fn ensemble_power(&self) -> Result<Vec<BatteryPower>, EnphaseError> {
+        self.fetch(get_ensemble_power)
+    }
+
+    pub fn ensemble_status(&self) -> Result<Option<EnsembleStatusResponse>, EnphaseError> {
+        self.fetch(get_ensemble_status)
+    }
+
+    pub fn grid_relay(&self) -> Result<Option<GridRelayResponse>, EnphaseError> {
+        self.fetch(get_grid_relay)
+    }
+
+    pub fn dry_contacts(&self) -> Result<Vec<DryContact>, EnphaseError> {
+        self.fetch(get_dry_contacts)
+    }
+
+    pub fn generator(&self) -> Result<Option<GeneratorResponse>, EnphaseError> {
+        self.fetch(get_generator)
+    }
+
+    pub fn grid_profile(&self) -> Result<Option<GridProfileResponse>, EnphaseError> {
+        self.fetch(get_grid_profile)
+    }
+
+    /// The Envoy's own network checks, given `timeout` to run instead of the usual read timeout.
+    pub fn network_display(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<NetworkDisplayResponse>, EnphaseError> {
+        self.fetch(|agent, url| get_network_display(agent, url, timeout))
+    }
+
+    pub fn date_time_display(&self) -> Result<Option<DateTimeDisplayResponse>, EnphaseError> {
+        self.fetch(get_date_time_display)
+    }
+
+    pub fn ev_charger(&self) -> Result<Option<EvChargerResponse>, EnphaseError> {
+        self.fetch(get_ev_charger)
+    }
+
+    pub fn wireless_display(&self) -> Result<Option<WirelessDisplayResponse>, EnphaseError> {
+        self.fetch(get_wireless_display)
+    }
+
+    pub fn new_scan(&self) -> Result<Option<NewScanResponse>, EnphaseError> {
+        self.fetch(get_new_scan)
+    }
+
+    pub fn ct_config(&self) -> Result<Vec<CtConfigResponse>, EnphaseError> {
+        self.fetch(get_ct_config)
+    }
+
+    pub fn generator_config(&self) -> Result<Option<GeneratorConfig>, EnphaseError> {
+        self.fetch(get_generator_config)
+    }
+
+    pub fn pv_limit(&self) -> Result<Option<PvLimitResponse>, EnphaseError> {
+        self.fetch(get_pv_limit)
+    }
+
+    pub fn tariff(&self) -> Result<Option<TariffResponse>, EnphaseError> {
+        self.fetch(get_tariff)
+    }
+
+    pub fn device_data(&self) -> Result<Vec<PdmDevice>, EnphaseError> {
+        self.fetch(get_device_data)
+    }
+
+    pub fn inverter_energy(&self) -> Result<BTreeMap<String, InverterEnergy>, EnphaseError> {
+        self.fetch(get_inverter_energy)
+    }
+
+    /// The `length` most recent entries in the event log.
+    pub fn events(&self, length: usize) -> Result<Vec<Event>, EnphaseError> {
+        self.fetch(|agent, url| get_events(agent, url, length))
+    }
+
+    pub fn livedata(&self) -> Result<LiveDataResponse, EnphaseError> {
+        self.fetch(get_livedata)
+    }
+
+    pub fn enable_livedata_stream(&self) -> Result<(), EnphaseError> {
+        self.fetch(enable_livedata_stream)
+    }
+
+    /// Retries opening the stream, but not once it's open; reconnecting is up to the caller.
+    pub fn stream_meter(&self) -> Result<MeterStream, EnphaseError> {
+        self.fetch(stream_meter)
+    }
+}
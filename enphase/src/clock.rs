@@ -0,0 +1,35 @@
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_privileged_json, EnphaseError};
+
+/// The Envoy's clock settings from `admin/lib/date_time_display.json`. Cellular-connected Envoys
+/// take their time from the network and leave out the NTP settings.
+#[derive(Deserialize, Debug)]
+pub struct DateTimeDisplayResponse {
+    /*
+    {
+        "tz": "America/New_York",
+        "date": "01/01/2023",
+        "time": "01:00",
+        "ntp_enabled": true,
+        "ntp_server": "pool.ntp.org"
+    }
+     */
+    /// an IANA name like `America/New_York`
+    #[serde(alias = "timezone")]
+    pub tz: String,
+    pub ntp_enabled: Option<bool>,
+    pub ntp_server: Option<String>,
+}
+
+/// `None` on Envoys without the endpoint, or when the token isn't allowed to read it.
+#[cfg(feature = "sync")]
+pub fn get_date_time_display(
+    agent: &Agent,
+    url: &str,
+) -> Result<Option<DateTimeDisplayResponse>, EnphaseError> {
+    get_privileged_json(agent, url, "admin/lib/date_time_display.json")
+}
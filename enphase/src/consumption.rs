@@ -0,0 +1,28 @@
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+use crate::production::ProductionSummaryResponse;
+#[cfg(feature = "sync")]
+use crate::{get_json, EnphaseError};
+
+/// `api/v1/consumption` has the same shape as `api/v1/production`.
+pub type ConsumptionSummaryResponse = ProductionSummaryResponse;
+
+/// Returns `None` when the Envoy has no consumption CTs. Depending on firmware that shows up as a
+/// 404, a 401, or a response that's all zeros.
+#[cfg(feature = "sync")]
+pub fn get_consumption_summary(
+    agent: &Agent,
+    url: &str,
+) -> Result<Option<ConsumptionSummaryResponse>, EnphaseError> {
+    let summary: ConsumptionSummaryResponse = match get_json(agent, url, "api/v1/consumption") {
+        Ok(summary) => summary,
+        Err(e) if matches!(e.status(), Some(401 | 404)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let unmetered = summary.watt_hours_today == 0
+        && summary.watt_hours_seven_days == 0
+        && summary.watt_hours_lifetime == 0
+        && summary.watts_now == 0;
+    Ok(if unmetered { None } else { Some(summary) })
+}
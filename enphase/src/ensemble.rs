@@ -0,0 +1,336 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_optional_json, EnphaseError};
+
+/// One group of devices from `ivp/ensemble/inventory`, which lists the Ensemble (storage and
+/// backup) hardware. Only the Encharge batteries are read for now.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type")]
+pub enum EnsembleInventoryResponse {
+    /*
+    [
+        {
+            "type": "ENCHARGE",
+            "devices": [
+                {
+                    "part_num": "830-01760-r37",
+                    "serial_num": "122100000000",
+                    "installed": 1660000000,
+                    "device_status": ["envoy.global.ok", "prop.done"],
+                    "last_rpt_date": 1688000000,
+                    "admin_state": 6,
+                    "admin_state_str": "ENCHG_STATE_READY",
+                    "operating": true,
+                    "communicating": true,
+                    "sleep_enabled": false,
+                    "percentFull": 95,
+                    "temperature": 29,
+                    "maxCellTemp": 30,
+                    "led_status": 17,
+                    "dc_switch_off": false,
+                    "encharge_rev": 2,
+                    "encharge_capacity": 3500,
+                    "encharge_rated_power_w": 3840,
+                    "charge_status": "charging"
+                }
+            ]
+        },
+        {"type": "ENPOWER", "devices": [...]}
+    ]
+     */
+    #[serde(rename = "ENCHARGE")]
+    Encharge { devices: Vec<EnchargeDevice> },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EnchargeDevice {
+    pub serial_num: String,
+    pub part_num: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub last_rpt_date: DateTime<Utc>,
+    pub device_status: Vec<String>,
+    pub admin_state_str: Option<String>,
+    #[serde(default)]
+    pub operating: bool,
+    #[serde(default)]
+    pub communicating: bool,
+    #[serde(default)]
+    pub sleep_enabled: bool,
+    /// state of charge, 0-100
+    #[serde(rename = "percentFull")]
+    pub percent_full: i32,
+    /// °C
+    pub temperature: i32,
+    #[serde(rename = "maxCellTemp")]
+    pub max_cell_temp: Option<i32>,
+    pub led_status: Option<i32>,
+    #[serde(default)]
+    pub dc_switch_off: bool,
+    /// Wh
+    pub encharge_capacity: i32,
+    /// W; not on older firmware
+    pub encharge_rated_power_w: Option<i32>,
+    /// like `charging`, `discharging`, or `idle`; not on older firmware
+    pub charge_status: Option<String>,
+}
+
+/// Lists the Ensemble devices; empty on systems without an Ensemble.
+#[cfg(feature = "sync")]
+pub fn get_ensemble_inventory(
+    agent: &Agent,
+    url: &str,
+) -> Result<Vec<EnsembleInventoryResponse>, EnphaseError> {
+    Ok(get_optional_json(agent, url, "ivp/ensemble/inventory")?.unwrap_or_default())
+}
+
+/// Real-time power for each battery, from `ivp/ensemble/power`.
+#[derive(Deserialize, Debug)]
+pub struct EnsemblePowerResponse {
+    /*
+    {
+        "devices:": [
+            {
+                "serial_num": "122100000000",
+                "real_power_mw": -1250000,
+                "apparent_power_mva": 1300000,
+                "soc": 95
+            }
+        ]
+    }
+     */
+    // the trailing colon really is in the key
+    #[serde(rename = "devices:", alias = "devices")]
+    pub devices: Vec<BatteryPower>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BatteryPower {
+    pub serial_num: String,
+    /// mW, negative while discharging
+    pub real_power_mw: i64,
+    /// mVA
+    pub apparent_power_mva: i64,
+    pub soc: Option<i32>,
+}
+
+/// Power for each battery; empty on systems without an Ensemble.
+#[cfg(feature = "sync")]
+pub fn get_ensemble_power(agent: &Agent, url: &str) -> Result<Vec<BatteryPower>, EnphaseError> {
+    let power: Option<EnsemblePowerResponse> = get_optional_json(agent, url, "ivp/ensemble/power")?;
+    Ok(power.map(|p| p.devices).unwrap_or_default())
+}
+
+/// Aggregate battery figures for the whole Ensemble, from `ivp/ensemble/secctrl`. Encharge
+/// (`ENC_`) and AC battery (`ACB_`) totals are only there on firmware that has them.
+#[derive(Deserialize, Debug)]
+pub struct EnsembleStatusResponse {
+    /*
+    {
+        "freq_bias_hz": 0.0,
+        "voltage_bias_v": 0.0,
+        "configured_backup_soc": 30,
+        "adjusted_backup_soc": 30,
+        "agg_soc": 95,
+        "Max_energy": 7000,
+        "ENC_agg_soc": 95,
+        "ENC_agg_soh": 100,
+        "ENC_agg_backup_energy": 2100,
+        "ENC_agg_avail_energy": 4550,
+        "Enc_commissioned_capacity": 7000,
+        "Enc_max_available_capacity": 7000,
+        "ACB_agg_soc": 0,
+        "ACB_agg_energy": 0,
+        "VLS_Limit": 0,
+        "agg_backup_energy": 2100,
+        "agg_avail_energy": 4550
+    }
+     */
+    /// state of charge across all batteries, 0-100
+    pub agg_soc: i32,
+    /// Wh
+    #[serde(rename = "Max_energy")]
+    pub max_energy: i64,
+    /// Wh held back for outages
+    pub agg_backup_energy: Option<i64>,
+    /// Wh
+    pub agg_avail_energy: Option<i64>,
+    pub configured_backup_soc: Option<i32>,
+    pub adjusted_backup_soc: Option<i32>,
+    #[serde(rename = "ENC_agg_soc")]
+    pub enc_agg_soc: Option<i32>,
+    /// state of health, 0-100
+    #[serde(rename = "ENC_agg_soh")]
+    pub enc_agg_soh: Option<i32>,
+    #[serde(rename = "ENC_agg_backup_energy")]
+    pub enc_agg_backup_energy: Option<i64>,
+    #[serde(rename = "ENC_agg_avail_energy")]
+    pub enc_agg_avail_energy: Option<i64>,
+    #[serde(rename = "Enc_commissioned_capacity")]
+    pub enc_commissioned_capacity: Option<i64>,
+    #[serde(rename = "Enc_max_available_capacity")]
+    pub enc_max_available_capacity: Option<i64>,
+    #[serde(rename = "ACB_agg_soc")]
+    pub acb_agg_soc: Option<i32>,
+    #[serde(rename = "ACB_agg_energy")]
+    pub acb_agg_energy: Option<i64>,
+}
+
+/// The Ensemble's aggregate battery status; `None` on systems without an Ensemble.
+#[cfg(feature = "sync")]
+pub fn get_ensemble_status(
+    agent: &Agent,
+    url: &str,
+) -> Result<Option<EnsembleStatusResponse>, EnphaseError> {
+    get_optional_json(agent, url, "ivp/ensemble/secctrl")
+}
+
+/// The Enpower transfer switch, from `ivp/ensemble/relay`.
+#[derive(Deserialize, Debug)]
+pub struct GridRelayResponse {
+    /*
+    {
+        "mains_admin_state": "closed",
+        "mains_oper_state": "closed",
+        "der1_state": 0,
+        "der2_state": 0,
+        "der3_state": 0,
+        "Enchg_grid_mode": "grid-tied",
+        "Solar_grid_mode": "grid-tied"
+    }
+     */
+    /// `closed` (on grid) or `open` (off grid), as commanded
+    pub mains_admin_state: String,
+    /// `closed` or `open`, as it actually is
+    pub mains_oper_state: String,
+    pub der1_state: Option<i32>,
+    pub der2_state: Option<i32>,
+    pub der3_state: Option<i32>,
+    /// `grid-tied`, `multimode-ongrid`, `multimode-offgrid`, ...
+    #[serde(rename = "Enchg_grid_mode")]
+    pub enchg_grid_mode: Option<String>,
+    #[serde(rename = "Solar_grid_mode")]
+    pub solar_grid_mode: Option<String>,
+}
+
+/// The grid relay's state; `None` on systems without an Enpower.
+#[cfg(feature = "sync")]
+pub fn get_grid_relay(agent: &Agent, url: &str) -> Result<Option<GridRelayResponse>, EnphaseError> {
+    get_optional_json(agent, url, "ivp/ensemble/relay")
+}
+
+/// One of the Enpower's dry contacts, from `ivp/ensemble/dry_contacts`.
+#[derive(Deserialize, Debug)]
+pub struct DryContact {
+    /*
+    {
+        "dry_contacts": [
+            {"id": "NC1", "status": "closed", "load_name": "Water heater"},
+            {"id": "NO1", "status": "open"}
+        ]
+    }
+    or on some firmware the bare array
+     */
+    pub id: String,
+    /// `open` or `closed`
+    pub status: String,
+    pub load_name: Option<String>,
+}
+
+#[cfg(feature = "sync")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DryContactsResponse {
+    Wrapped { dry_contacts: Vec<DryContact> },
+    Bare(Vec<DryContact>),
+}
+
+/// The dry contacts; empty on systems without an Enpower.
+#[cfg(feature = "sync")]
+pub fn get_dry_contacts(agent: &Agent, url: &str) -> Result<Vec<DryContact>, EnphaseError> {
+    let response: Option<DryContactsResponse> =
+        get_optional_json(agent, url, "ivp/ensemble/dry_contacts")?;
+    Ok(match response {
+        Some(DryContactsResponse::Wrapped { dry_contacts }) => dry_contacts,
+        Some(DryContactsResponse::Bare(dry_contacts)) => dry_contacts,
+        None => Vec::new(),
+    })
+}
+
+/// A generator connected through the Enpower, from `ivp/ensemble/generator`.
+#[derive(Deserialize, Debug)]
+pub struct GeneratorResponse {
+    /*
+    {
+        "admin_state": "auto",
+        "oper_state": "off",
+        "admin_mode": "auto",
+        "schedule": 0,
+        "start_source": "no_source",
+        "start_reason": "none",
+        "stop_reason": "none"
+    }
+     */
+    /// `on`, `off`, or `auto`, as configured
+    pub admin_state: String,
+    /// `on` or `off`, as it actually is
+    pub oper_state: String,
+    pub admin_mode: Option<String>,
+    pub schedule: Option<i32>,
+    pub start_source: Option<String>,
+    pub start_reason: Option<String>,
+    pub stop_reason: Option<String>,
+}
+
+/// The generator's state; `None` when no generator is configured.
+#[cfg(feature = "sync")]
+pub fn get_generator(agent: &Agent, url: &str) -> Result<Option<GeneratorResponse>, EnphaseError> {
+    get_optional_json(agent, url, "ivp/ensemble/generator")
+}
+
+/// How the generator is set up, from `ivp/ss/gen_config`.
+#[cfg(feature = "sync")]
+#[derive(Deserialize, Debug)]
+struct GeneratorConfigResponse {
+    /*
+    {
+        "gen_config": {
+            "type": "portable",
+            "rated_power": 7500,
+            "start_soc": 20,
+            "stop_soc": 80
+        }
+    }
+     */
+    #[serde(default)]
+    gen_config: Option<GeneratorConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GeneratorConfig {
+    #[serde(rename = "type")]
+    pub generator_type: Option<String>,
+    /// watts
+    pub rated_power: Option<f64>,
+    /// the battery charge, in percent, at which the generator starts
+    pub start_soc: Option<f64>,
+    /// and stops
+    pub stop_soc: Option<f64>,
+}
+
+/// `None` when there's no generator configured, which some firmware shows as a 404 and some as an
+/// empty response.
+#[cfg(feature = "sync")]
+pub fn get_generator_config(
+    agent: &Agent,
+    url: &str,
+) -> Result<Option<GeneratorConfig>, EnphaseError> {
+    let config: Option<GeneratorConfigResponse> =
+        get_optional_json(agent, url, "ivp/ss/gen_config")?;
+    Ok(config.and_then(|config| config.gen_config))
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum EnphaseError {
+    /// The request failed in transport or came back with a non-2xx status. Boxed because
+    /// `ureq::Error` carries the whole response.
+    #[cfg(feature = "sync")]
+    Http(Box<ureq::Error>),
+    /// The same, from the async client.
+    #[cfg(feature = "async")]
+    Request(reqwest::Error),
+    /// The response body wasn't the JSON we expected.
+    Deserialize {
+        url: String,
+        source: serde_json::Error,
+    },
+    /// Reading a response body (or any other I/O) failed.
+    Io(std::io::Error),
+    /// Authentication with the Envoy or Enlighten failed.
+    Auth(String),
+    /// A value reported by the Envoy couldn't be interpreted.
+    Parse(String),
+    /// The Envoy reported a timezone we don't know about.
+    TimezoneNotFound(String),
+}
+
+impl EnphaseError {
+    /// The HTTP status code, if the Envoy answered with a non-2xx status.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            #[cfg(feature = "sync")]
+            EnphaseError::Http(e) => match e.as_ref() {
+                ureq::Error::Status(code, _) => Some(*code),
+                ureq::Error::Transport(_) => None,
+            },
+            #[cfg(feature = "async")]
+            EnphaseError::Request(e) => e.status().map(|status| status.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Whether the same request might work if tried again: the connection failed, the response
+    /// was cut off, or the Envoy answered with a 5xx. A 4xx or an unparseable response won't
+    /// change, and neither will an untrusted certificate.
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            #[cfg(feature = "sync")]
+            EnphaseError::Http(e) => match e.as_ref() {
+                ureq::Error::Status(code, _) => *code >= 500,
+                ureq::Error::Transport(transport) => {
+                    matches!(
+                        transport.kind(),
+                        ureq::ErrorKind::Dns
+                            | ureq::ErrorKind::ConnectionFailed
+                            | ureq::ErrorKind::Io
+                    ) && !self.is_untrusted_certificate()
+                }
+            },
+            #[cfg(feature = "async")]
+            EnphaseError::Request(e) => match e.status() {
+                Some(status) => status.is_server_error(),
+                None => e.is_connect() || e.is_timeout() || e.is_body(),
+            },
+            EnphaseError::Io(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the request failed because the server's TLS certificate isn't trusted, as happens
+    /// with the Envoy's self-signed one unless [`crate::tls::insecure_tls_config`] is used.
+    #[cfg(feature = "sync")]
+    pub fn is_untrusted_certificate(&self) -> bool {
+        let mut source = std::error::Error::source(self);
+        while let Some(e) = source {
+            // rustls errors reach us wrapped in an io::Error, which doesn't report them as its source
+            let e = match e
+                .downcast_ref::<std::io::Error>()
+                .and_then(|io| io.get_ref())
+            {
+                Some(inner) => inner as &(dyn std::error::Error + 'static),
+                None => e,
+            };
+            if let Some(rustls::Error::InvalidCertificate(_)) = e.downcast_ref() {
+                return true;
+            }
+            source = e.source();
+        }
+        false
+    }
+}
+
+impl fmt::Display for EnphaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // ureq already includes the URL and, for status errors, the status code
+            #[cfg(feature = "sync")]
+            EnphaseError::Http(e) => write!(f, "{}", e),
+            // reqwest does too
+            #[cfg(feature = "async")]
+            EnphaseError::Request(e) => write!(f, "{}", e),
+            EnphaseError::Deserialize { url, source } => {
+                write!(f, "{}: unexpected response: {}", url, source)
+            }
+            EnphaseError::Io(e) => write!(f, "I/O error: {}", e),
+            EnphaseError::Auth(msg) => write!(f, "authentication failed: {}", msg),
+            EnphaseError::Parse(msg) => write!(f, "could not parse {}", msg),
+            EnphaseError::TimezoneNotFound(zone) => write!(f, "unknown timezone {:?}", zone),
+        }
+    }
+}
+
+impl std::error::Error for EnphaseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "sync")]
+            EnphaseError::Http(e) => Some(e),
+            #[cfg(feature = "async")]
+            EnphaseError::Request(e) => Some(e),
+            EnphaseError::Deserialize { source, .. } => Some(source),
+            EnphaseError::Io(e) => Some(e),
+            EnphaseError::Auth(_) | EnphaseError::Parse(_) | EnphaseError::TimezoneNotFound(_) => {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+impl From<ureq::Error> for EnphaseError {
+    fn from(e: ureq::Error) -> Self {
+        EnphaseError::Http(Box::new(e))
+    }
+}
+
+#[cfg(feature = "async")]
+impl From<reqwest::Error> for EnphaseError {
+    fn from(e: reqwest::Error) -> Self {
+        EnphaseError::Request(e)
+    }
+}
+
+impl From<std::io::Error> for EnphaseError {
+    fn from(e: std::io::Error) -> Self {
+        EnphaseError::Io(e)
+    }
+}
@@ -0,0 +1,35 @@
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_optional_json, EnphaseError};
+
+/// The EV charger's state from `ivp/peb/reglsoc`, on systems with an IQ EV Charger.
+#[derive(Deserialize, Debug)]
+pub struct EvChargerResponse {
+    /*
+    {
+        "charging": true,
+        "power_w": 7200,
+        "soc_target": 80,
+        "session_energy_wh": 12500
+    }
+     */
+    pub charging: Option<bool>,
+    /// watts being drawn right now
+    #[serde(alias = "charging_power")]
+    pub power_w: Option<f64>,
+    /// percent
+    #[serde(alias = "target_soc")]
+    pub soc_target: Option<f64>,
+    /// watt hours delivered this charging session
+    #[serde(alias = "session_energy")]
+    pub session_energy_wh: Option<f64>,
+}
+
+/// `None` on systems without an EV charger, where the endpoint is a 404.
+#[cfg(feature = "sync")]
+pub fn get_ev_charger(agent: &Agent, url: &str) -> Result<Option<EvChargerResponse>, EnphaseError> {
+    get_optional_json(agent, url, "ivp/peb/reglsoc")
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Deserializer};
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_json, EnphaseError};
+
+/// The event log behind the Envoy's Events page, in the shape DataTables wants.
+#[cfg(feature = "sync")]
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct EventTableResponse {
+    /*
+    {
+        "sEcho": 1,
+        "iTotalRecords": 8495,
+        "iTotalDisplayRecords": 8495,
+        "aaData": [
+            [8495, "Grid instability detected", "Envoy", "Thu Jun 29, 2023 10:34 AM PDT"],
+            [8494, "DC Resistance Low - Power Off set", "pcu SN: 121234567890", "Thu Jun 29, 2023 10:30 AM PDT"],
+            ...
+        ]
+    }
+     */
+    aa_data: Vec<Event>,
+}
+
+/// One row of the event log, newest first.
+#[derive(Deserialize, Debug)]
+pub struct Event {
+    /// counts up from the first event the Envoy logged
+    #[serde(deserialize_with = "number_or_string")]
+    pub id: i64,
+    pub description: String,
+    /// `Envoy`, or the device type and serial number, like `pcu SN: 121234567890`
+    pub device: String,
+    /// in the Envoy's time zone, like `Thu Jun 29, 2023 10:34 AM PDT`
+    pub date: String,
+}
+
+impl Event {
+    /// The serial number from `device`, or all of it when there isn't one.
+    pub fn serial(&self) -> &str {
+        match self.device.split_once("SN:") {
+            Some((_, serial)) => serial.trim(),
+            None => &self.device,
+        }
+    }
+}
+
+/// Some firmware sends the ids as strings.
+fn number_or_string<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(i64),
+        String(String),
+    }
+    match Id::deserialize(deserializer)? {
+        Id::Number(id) => Ok(id),
+        Id::String(id) => id.trim().parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// The `length` most recent events.
+#[cfg(feature = "sync")]
+pub fn get_events(agent: &Agent, url: &str, length: usize) -> Result<Vec<Event>, EnphaseError> {
+    let path = format!("datatab/event_dt.rb?start=0&length={}", length);
+    let response: EventTableResponse = get_json(agent, url, &path)?;
+    Ok(response.aa_data)
+}
@@ -0,0 +1,52 @@
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_privileged_json, EnphaseError};
+
+/// The grid profiles from `installer/agf/index.json`: the rules the inverters follow for voltage
+/// and frequency trips, set per utility.
+#[derive(Deserialize, Debug)]
+pub struct GridProfileResponse {
+    /*
+    {
+        "selected_profile": "IEEE 1547:2018 SA:1.2.3",
+        "profile_status": "Completed",
+        "profiles": [
+            {"id": "5e9e0fc1", "name": "IEEE 1547:2018 SA", "version": "1.2.3", ...},
+            {"id": "2b3c75e0", "name": "CA Rule 21 201902 VV VW FW", "version": "1.2.10", ...}
+        ]
+    }
+     */
+    pub selected_profile: String,
+    #[serde(default)]
+    pub profiles: Vec<GridProfile>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GridProfile {
+    pub id: Option<String>,
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl GridProfileResponse {
+    /// The entry in `profiles` for the selected profile, which is named `<name>:<version>`.
+    pub fn selected(&self) -> Option<&GridProfile> {
+        self.profiles.iter().find(|profile| match &profile.version {
+            Some(version) => self.selected_profile == format!("{}:{}", profile.name, version),
+            None => self.selected_profile == profile.name,
+        })
+    }
+}
+
+/// Only installers may read the grid profiles, so `None` when the Envoy refuses as well as when
+/// it doesn't have the endpoint.
+#[cfg(feature = "sync")]
+pub fn get_grid_profile(
+    agent: &Agent,
+    url: &str,
+) -> Result<Option<GridProfileResponse>, EnphaseError> {
+    get_privileged_json(agent, url, "installer/agf/index.json")
+}
@@ -0,0 +1,439 @@
+use chrono::{DateTime, Utc};
+use serde::{de::Error as _, Deserialize, Deserializer};
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_json, EnphaseError};
+
+#[derive(Deserialize, Debug)]
+pub struct HomeResponse {
+    /*
+    {
+    "software_build_epoch":1234567890,
+    "is_nonvoy":false,
+    "db_size":"12 MB",
+    "db_percent_full":"1",
+    "timezone":"America/New_York",
+    "current_date":"01/01/2023",
+    "current_time":"01:00",
+    "network":{
+        "web_comm":true,
+        "ever_reported_to_enlighten":true,
+        "last_enlighten_report_time":1234567890,
+        "primary_interface":"wlan0",
+        "interfaces":[
+            {
+                "type":"ethernet",
+                "interface":"eth0",
+                "mac":"00:11:22:33:44:55",
+                "dhcp":true,"ip":"169.169.169.169",
+                "signal_strength":0,
+                "signal_strength_max":1,
+                "carrier":false
+            },{
+                "signal_strength":1,
+                "signal_strength_max":5,
+                "type":"wifi",
+                "interface":"wlan0",
+                "mac":"11:22:33:44:55:66",
+                "dhcp":true,
+                "ip":"192.168.1.1",
+                "carrier":true,
+                "supported":true,
+                "present":true,
+                "configured":true,
+                "status":"connected"
+            }
+        ]
+    },
+    "comm":{"num":1,"level":1},
+    "alerts":[],
+    "update_status":"satisfied",
+    "wifi":{"provisioned":true,"supported":true,"status":"connected"},
+    "enpower":{"connected":true,"grid_status":"closed"}}
+     */
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub software_build_epoch: DateTime<Utc>,
+
+    /// whether this is an IQ Gateway without the Envoy's metering
+    #[serde(default)]
+    pub is_nonvoy: bool,
+
+    pub current_date: String,
+
+    pub current_time: String,
+
+    pub timezone: String,
+
+    #[serde(deserialize_with = "decode_memory_string")]
+    /// bytes
+    pub db_size: i64,
+
+    #[serde(deserialize_with = "string_to_i32")]
+    pub db_percent_full: i32,
+
+    pub network: HomeNetworkResponse,
+    pub comm: HomeCommResponse,
+
+    #[serde(deserialize_with = "decode_alerts")]
+    pub alerts: Vec<Alert>,
+
+    pub update_status: UpdateStatus,
+
+    // only newer firmware sends these
+    pub wifi: Option<HomeWifi>,
+    pub enpower: Option<HomeEnpower>,
+}
+
+/// Whether the Envoy's Wi-Fi has been set up.
+#[derive(Deserialize, Debug)]
+pub struct HomeWifi {
+    pub provisioned: Option<bool>,
+    pub supported: Option<bool>,
+    /// like `connected`
+    pub status: Option<String>,
+}
+
+/// The Enpower (IQ System Controller), which switches the house between the grid and batteries.
+#[derive(Deserialize, Debug)]
+pub struct HomeEnpower {
+    pub connected: Option<bool>,
+    /// `closed` when the house is on the grid, `open` when it's islanded
+    pub grid_status: Option<String>,
+}
+
+/// Whether the Envoy's firmware is up to date.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "String")]
+pub enum UpdateStatus {
+    Satisfied,
+    UpdateAvailable,
+    Updating,
+    /// anything newer firmware might send
+    Unknown(String),
+}
+
+impl UpdateStatus {
+    /// The status as the Envoy sends it, like `satisfied`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            UpdateStatus::Satisfied => "satisfied",
+            UpdateStatus::UpdateAvailable => "update_available",
+            UpdateStatus::Updating => "updating",
+            UpdateStatus::Unknown(status) => status,
+        }
+    }
+}
+
+impl From<String> for UpdateStatus {
+    fn from(status: String) -> Self {
+        match status.as_str() {
+            "satisfied" => UpdateStatus::Satisfied,
+            "update_available" => UpdateStatus::UpdateAvailable,
+            "updating" => UpdateStatus::Updating,
+            _ => UpdateStatus::Unknown(status),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HomeNetworkResponse {
+    pub web_comm: bool,
+    pub ever_reported_to_enlighten: bool,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub last_enlighten_report_time: DateTime<Utc>,
+    pub primary_interface: String,
+    pub interfaces: Vec<NetworkInterface>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NetworkInterface {
+    /// `ethernet`, `wifi`, or `cellular`
+    #[serde(rename = "type")]
+    pub interface_type: String,
+    pub interface: String,
+    pub mac: String,
+    pub dhcp: bool,
+    pub ip: String,
+    pub signal_strength: i32,
+    pub signal_strength_max: i32,
+    pub carrier: bool,
+    // the rest are only reported for wifi
+    pub supported: Option<bool>,
+    pub present: Option<bool>,
+    pub configured: Option<bool>,
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HomeCommResponse {
+    /*
+    {
+        "num": 6,
+        "level": 5,
+        "pcu": {"num": 5, "level": 5},
+        "acb": {"num": 0, "level": 0},
+        "nsrb": {"num": 0, "level": 0},
+        "encharge": [{"num": 1, "level": 5, "level_24g": 5, "level_subg": 5}],
+        "enpower": {"num": 1, "level": 5}
+    }
+     */
+    /// across every kind of device
+    pub num: i32,
+    pub level: i32,
+    // only firmware 7 and later break it down by kind of device
+    pub pcu: Option<HomeCommLevel>,
+    pub acb: Option<HomeCommLevel>,
+    pub nsrb: Option<HomeCommLevel>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub encharge: Vec<HomeCommLevel>,
+    pub enpower: Option<HomeCommLevel>,
+}
+
+/// How many devices of one kind the Envoy is talking to, and how well, from 0 to 5.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct HomeCommLevel {
+    pub num: i32,
+    pub level: i32,
+}
+
+impl HomeCommResponse {
+    /// Each kind of device that's reported, like `("pcu", level)`. Encharges listed separately are
+    /// added up, at the worst of their levels.
+    pub fn by_device_type(&self) -> Vec<(&'static str, HomeCommLevel)> {
+        let mut levels = Vec::new();
+        for (device_type, level) in [
+            ("pcu", &self.pcu),
+            ("acb", &self.acb),
+            ("nsrb", &self.nsrb),
+            ("enpower", &self.enpower),
+        ] {
+            if let Some(level) = level {
+                levels.push((device_type, *level));
+            }
+        }
+        let encharge = self.encharge.iter().copied().reduce(|a, b| HomeCommLevel {
+            num: a.num + b.num,
+            level: a.level.min(b.level),
+        });
+        if let Some(level) = encharge {
+            levels.push(("encharge", level));
+        }
+        levels
+    }
+}
+
+/// `encharge` is a list with one entry per Encharge on some firmware, and a single object on
+/// others.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<HomeCommLevel>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(HomeCommLevel),
+        Many(Vec<HomeCommLevel>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(level) => vec![level],
+        OneOrMany::Many(levels) => levels,
+    })
+}
+
+/// Something the Envoy wants looked at. Enphase doesn't document these and they differ between
+/// firmware versions, so every part is optional and the original JSON is kept too.
+#[derive(Debug)]
+pub struct Alert {
+    /// `code`, `id`, or `msg_key`
+    pub code: Option<String>,
+    /// `severity` or `level`, like `warning`
+    pub severity: Option<String>,
+    /// the device it's about, if it's not the Envoy itself
+    pub serial_number: Option<String>,
+    /// when it was raised
+    pub timestamp: Option<DateTime<Utc>>,
+    pub raw: String,
+}
+
+impl Alert {
+    fn from_json(alert: serde_json::Value) -> Alert {
+        let text = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| alert.get(key))
+                .and_then(|value| match value {
+                    serde_json::Value::String(s) => Some(s.clone()),
+                    serde_json::Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                })
+        };
+        Alert {
+            code: text(&["code", "id", "msg_key"]),
+            severity: text(&["severity", "level"]),
+            serial_number: text(&["serial_num", "serial_number", "serial"]),
+            timestamp: ["timestamp", "time", "date"]
+                .iter()
+                .find_map(|key| alert.get(key)?.as_i64())
+                .and_then(|seconds| DateTime::from_timestamp(seconds, 0)),
+            raw: alert.to_string(),
+        }
+    }
+}
+
+fn decode_alerts<'de, D>(deserializer: D) -> Result<Vec<Alert>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Array(alerts) => Ok(alerts.into_iter().map(Alert::from_json).collect()),
+        // some firmware sends null rather than an empty array
+        serde_json::Value::Null => Ok(Vec::new()),
+        _ => Err(D::Error::custom("expected an array")),
+    }
+}
+
+fn string_to_i32<'de, D>(deserializer: D) -> Result<i32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer)?
+        .parse::<i32>()
+        .map_err(D::Error::custom)
+}
+
+fn decode_memory_string<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    let mut iter = s.split_ascii_whitespace();
+    let number = iter
+        .next()
+        .ok_or_else(|| D::Error::custom("empty memory size"))?
+        .parse::<i64>()
+        .map_err(D::Error::custom)?;
+    // a bare number is bytes
+    let multiple: i64 = match iter.next().map(|unit| unit.to_ascii_uppercase()).as_deref() {
+        None | Some("B") => 1,
+        Some("KB") => 1 << 10,
+        Some("MB") => 1 << 20,
+        Some("GB") => 1 << 30,
+        Some("TB") => 1 << 40,
+        // 8 EB is the most an i64 holds, so a few thousand PB is as far as this goes
+        Some("PB") => 1 << 50,
+        Some(_) => {
+            return Err(D::Error::custom(format!(
+                "memory size {:?} has an unknown unit",
+                s
+            )))
+        }
+    };
+    number
+        .checked_mul(multiple)
+        .ok_or_else(|| D::Error::custom(format!("memory size {:?} is too large", s)))
+}
+
+#[cfg(feature = "sync")]
+#[tracing::instrument(level = "debug", skip(agent))]
+pub fn get_home(agent: &Agent, url: &str) -> Result<HomeResponse, EnphaseError> {
+    get_json(agent, url, "home.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Debug)]
+    struct DbSize {
+        #[serde(deserialize_with = "decode_memory_string")]
+        db_size: i64,
+    }
+
+    /// home.json from firmware 7, with `change` applied.
+    fn home(
+        change: impl FnOnce(&mut serde_json::Value),
+    ) -> Result<HomeResponse, serde_json::Error> {
+        let mut home = serde_json::json!({
+            "software_build_epoch": 1234567890,
+            "is_nonvoy": false,
+            "db_size": "12 MB",
+            "db_percent_full": "1",
+            "timezone": "America/New_York",
+            "current_date": "01/01/2023",
+            "current_time": "01:00",
+            "network": {
+                "web_comm": true,
+                "ever_reported_to_enlighten": true,
+                "last_enlighten_report_time": 1234567890,
+                "primary_interface": "eth0",
+                "interfaces": [{
+                    "type": "ethernet",
+                    "interface": "eth0",
+                    "mac": "00:11:22:33:44:55",
+                    "dhcp": true,
+                    "ip": "192.168.1.2",
+                    "signal_strength": 1,
+                    "signal_strength_max": 1,
+                    "carrier": true
+                }]
+            },
+            "comm": {"num": 1, "level": 5},
+            "alerts": [],
+            "update_status": "satisfied"
+        });
+        change(&mut home);
+        serde_json::from_value(home)
+    }
+
+    #[test]
+    fn percent_full_must_be_a_number() {
+        let error = home(|home| home["db_percent_full"] = "abc".into()).unwrap_err();
+        assert!(error.to_string().contains("invalid digit"), "{}", error);
+    }
+
+    #[test]
+    fn null_alerts_are_empty() {
+        let home = home(|home| home["alerts"] = serde_json::Value::Null).unwrap();
+        assert!(home.alerts.is_empty());
+    }
+
+    fn db_size(size: &str) -> Result<i64, serde_json::Error> {
+        serde_json::from_value::<DbSize>(serde_json::json!({ "db_size": size })).map(|d| d.db_size)
+    }
+
+    #[test]
+    fn memory_sizes() {
+        for (size, bytes) in [
+            ("12 MB", 12 << 20),
+            ("1 GB", 1 << 30),
+            ("4 KB", 4 << 10),
+            ("7 B", 7),
+            ("2 PB", 2 << 50),
+            ("3 tb", 3 << 40),
+            ("  12   MB  ", 12 << 20),
+            ("512", 512),
+            // the largest that fits in an i64
+            ("8191 PB", 8191 << 50),
+        ] {
+            assert_eq!(db_size(size).unwrap(), bytes, "{:?}", size);
+        }
+    }
+
+    #[test]
+    fn bad_memory_sizes() {
+        for (size, message) in [
+            ("", "empty memory size"),
+            (" ", "empty memory size"),
+            ("\t\n", "empty memory size"),
+            ("big", "invalid digit"),
+            ("MB", "invalid digit"),
+            ("5 XB", "has an unknown unit"),
+            ("8192 PB", "is too large"),
+            ("9000 PB", "is too large"),
+        ] {
+            let error = db_size(size).unwrap_err();
+            assert!(error.to_string().contains(message), "{:?}: {}", size, error);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use tracing::debug;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::EnphaseError;
+
+#[derive(Deserialize, Debug)]
+pub struct InfoResponse {
+    pub time: i64,
+    pub device: InfoDevice,
+    #[serde(rename = "web-tokens", default)]
+    pub web_tokens: bool,
+    #[serde(rename = "package", default)]
+    pub packages: Vec<InfoPackage>,
+    /*
+    <envoy_info>
+      <time>1688000000</time>
+      <device>
+        <sn>122012345678</sn>
+        <pn>800-00555-r03</pn>
+        <software>D7.6.175</software>
+        <euaid>4c8675</euaid>
+        <seqnum>0</seqnum>
+        <apiver>1</apiver>
+        <imeter>true</imeter>
+      </device>
+      <web-tokens>true</web-tokens>
+      <package pname="app">
+        <pn>500-00001-r01</pn>
+        <version>07.06.175</version>
+        <build>release-7.6.175-23Jan21-010301</build>
+      </package>
+    </envoy_info>
+     */
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InfoDevice {
+    #[serde(rename = "sn")]
+    pub serial_number: String,
+    #[serde(rename = "pn")]
+    pub part_number: String,
+    pub software: String,
+    #[serde(default)]
+    pub imeter: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InfoPackage {
+    #[serde(rename = "@pname")]
+    pub name: String,
+    pub version: String,
+    pub build: String,
+}
+
+/// Fetches `info.xml`, which most firmware serves without auth. Older Envoys don't have it, so a
+/// 404 is `None`.
+#[cfg(feature = "sync")]
+pub fn get_info(agent: &Agent, url: &str) -> Result<Option<InfoResponse>, EnphaseError> {
+    let full_url = format!("{}/info.xml", url);
+    debug!("GET {}", full_url);
+    let body = match agent.get(&full_url).call() {
+        Err(ureq::Error::Status(404, _)) => return Ok(None),
+        result => result?.into_string()?,
+    };
+    debug!("{} sent {} bytes", full_url, body.len());
+    quick_xml::de::from_str(&body).map(Some).map_err(|e| {
+        debug!("couldn't parse {}: {}", full_url, body);
+        EnphaseError::Parse(format!("{}: {}", full_url, e))
+    })
+}
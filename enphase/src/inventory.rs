@@ -0,0 +1,84 @@
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{de::Error as _, Deserialize, Deserializer};
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_json, EnphaseError};
+
+/// One group of devices from `inventory.json`. The Envoy groups devices by type, and the types
+/// report different details, so only what they have in common is required here.
+#[derive(Deserialize, Debug)]
+pub struct InventoryResponse {
+    /*
+    [
+        {
+            "type": "PCU",
+            "devices": [
+                {
+                    "part_num": "800-00631-r02",
+                    "installed": "1601234567",
+                    "serial_num": "121234567890",
+                    "device_status": ["envoy.global.ok"],
+                    "last_rpt_date": "1688000000",
+                    "admin_state": 1,
+                    "dev_type": 1,
+                    "created_date": "1601234567",
+                    "img_load_date": "1601234567",
+                    "img_pnum_running": "520-00082-r01-v04.27.04",
+                    "ptpn": "540-00169-r01-v04.27.09",
+                    "chaneid": 1627390225,
+                    "device_control": [{"gficlearset": false}],
+                    "producing": true,
+                    "communicating": true,
+                    "provisioned": true,
+                    "operating": true
+                }
+            ]
+        },
+        {"type": "ACB", "devices": []},
+        {"type": "NSRB", "devices": []}
+    ]
+     */
+    /// `PCU` (microinverters), `ACB` (AC batteries), `NSRB` (relays), ...
+    #[serde(rename = "type")]
+    pub device_type: String,
+    pub devices: Vec<InventoryDevice>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InventoryDevice {
+    pub serial_num: String,
+    pub part_num: String,
+    #[serde(deserialize_with = "string_to_timestamp")]
+    pub installed: DateTime<Utc>,
+    #[serde(deserialize_with = "string_to_timestamp")]
+    pub last_rpt_date: DateTime<Utc>,
+    pub device_status: Vec<String>,
+    /// the firmware image running, like `520-00082-r01-v04.27.04`
+    pub img_pnum_running: Option<String>,
+    #[serde(default)]
+    pub producing: bool,
+    #[serde(default)]
+    pub communicating: bool,
+    #[serde(default)]
+    pub provisioned: bool,
+    pub operating: Option<bool>,
+}
+
+/// The inventory reports its dates as strings of epoch seconds.
+fn string_to_timestamp<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    let seconds = s.parse::<i64>().map_err(D::Error::custom)?;
+    Utc.timestamp_opt(seconds, 0)
+        .single()
+        .ok_or_else(|| D::Error::custom(format!("timestamp {} is out of range", seconds)))
+}
+
+#[cfg(feature = "sync")]
+pub fn get_inventory(agent: &Agent, url: &str) -> Result<Vec<InventoryResponse>, EnphaseError> {
+    get_json(agent, url, "inventory.json")
+}
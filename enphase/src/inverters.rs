@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_json, EnphaseError};
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InvertersResponse {
+    pub serial_number: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub last_report_date: DateTime<Utc>,
+    pub last_report_watts: i32,
+    pub max_report_watts: i32,
+    /// only firmware 8 sends this, like 1 for a microinverter
+    pub dev_type: Option<i32>,
+    /// anything else about the last report that newer firmware adds
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+    /*{
+    "0": {
+        "serialNumber": "123456789012",
+        "lastReportDate": 1688000000,
+        "devType": 1,
+        "lastReportWatts": 123,
+        "maxReportWatts": 234
+    }
+     */
+}
+
+#[cfg(feature = "sync")]
+#[tracing::instrument(level = "debug", skip(agent))]
+pub fn get_inverters(agent: &Agent, url: &str) -> Result<Vec<InvertersResponse>, EnphaseError> {
+    get_json(agent, url, "api/v1/production/inverters")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn firmware_7_inverters() {
+        let inverters: Vec<InvertersResponse> = serde_json::from_str(
+            r#"[
+                {"serialNumber": "123456789012", "lastReportDate": 1688000000, "lastReportWatts": 123, "maxReportWatts": 234},
+                {"serialNumber": "123456789013", "lastReportDate": 1688000100, "lastReportWatts": 120, "maxReportWatts": 234}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(inverters.len(), 2);
+        assert_eq!(inverters[0].serial_number, "123456789012");
+        assert_eq!(inverters[0].last_report_date.timestamp(), 1688000000);
+        assert_eq!(inverters[0].last_report_watts, 123);
+        assert_eq!(inverters[0].max_report_watts, 234);
+        assert_eq!(inverters[0].dev_type, None);
+        assert!(inverters[0].extra.is_empty());
+    }
+
+    #[test]
+    fn firmware_8_inverters() {
+        let inverters: Vec<InvertersResponse> =
+            serde_json::from_str(include_str!("../fixtures/inverters_d8.json")).unwrap();
+        assert_eq!(inverters[0].serial_number, "482312345678");
+        assert_eq!(inverters[0].last_report_watts, 281);
+        assert_eq!(inverters[0].dev_type, Some(1));
+        assert_eq!(inverters[0].extra.len(), 2);
+        assert_eq!(inverters[0].extra["lastReportVolts"], 240.5);
+        assert_eq!(inverters[0].extra["reportCount"], 3);
+    }
+}
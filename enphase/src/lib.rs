@@ -0,0 +1,118 @@
+//! Client library for reading data from a local Enphase Envoy gateway.
+//!
+//! The Envoy serves a handful of JSON endpoints behind HTTP digest auth or, on firmware D7 and
+//! later, a bearer token. Get the header from [`auth::get_auth_header`] (or `Bearer` plus the token
+//! from [`auth::fetch_enlighten_token`]) and hand it to [`EnvoyClient::with_authorization`], which
+//! has a method for each endpoint. The `get_*` functions in each module do the same with any
+//! [`ureq::Agent`] that attaches the header itself.
+//!
+//! That's the blocking client, on by default as the `sync` feature. The `async` feature adds
+//! [`nonblocking`], with async versions of the main requests on `reqwest`.
+
+#[cfg(any(feature = "sync", feature = "async"))]
+pub mod auth;
+#[cfg(feature = "sync")]
+pub mod client;
+pub mod clock;
+pub mod consumption;
+pub mod ensemble;
+pub mod error;
+pub mod ev_charger;
+pub mod events;
+pub mod grid_profile;
+pub mod home;
+pub mod info;
+pub mod inventory;
+pub mod inverters;
+pub mod livedata;
+pub mod meters;
+pub mod network_display;
+pub mod newscan;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+pub mod pdm;
+pub mod production;
+pub mod pvlimit;
+pub mod retry;
+#[cfg(feature = "sync")]
+pub mod stream;
+pub mod tariff;
+#[cfg(feature = "sync")]
+pub mod tls;
+pub mod wireless;
+
+#[cfg(feature = "sync")]
+pub use client::EnvoyClient;
+pub use error::EnphaseError;
+
+#[cfg(feature = "sync")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "sync")]
+use tracing::debug;
+#[cfg(feature = "sync")]
+use ureq::{Agent, Request};
+
+/// GETs `<url>/<path>` and decodes the JSON body.
+#[cfg(feature = "sync")]
+fn get_json<T: DeserializeOwned>(agent: &Agent, url: &str, path: &str) -> Result<T, EnphaseError> {
+    call_json(agent.get(&format!("{}/{}", url, path)))
+}
+
+/// Sends `request` and decodes the JSON body.
+#[cfg(feature = "sync")]
+fn call_json<T: DeserializeOwned>(request: Request) -> Result<T, EnphaseError> {
+    let full_url = request.url().to_string();
+    debug!("{} {}", request.method(), full_url);
+    let response = request.call()?;
+    let status = response.status();
+    let body = response.into_string()?;
+    debug!("{} answered {} with {} bytes", full_url, status, body.len());
+    serde_json::from_str(&body).map_err(|source| {
+        debug!("couldn't parse {}: {}", full_url, body);
+        EnphaseError::Deserialize {
+            url: full_url,
+            source,
+        }
+    })
+}
+
+/// Like [`get_json`], for endpoints that only exist on some Envoys: a 404 is `None`.
+#[cfg(feature = "sync")]
+fn get_optional_json<T: DeserializeOwned>(
+    agent: &Agent,
+    url: &str,
+    path: &str,
+) -> Result<Option<T>, EnphaseError> {
+    match get_json(agent, url, path) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.status() == Some(404) => {
+            debug!("{}: not on this Envoy, skipping", e);
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Like [`get_optional_json`], for endpoints that need installer credentials: the Envoy refusing
+/// them is `None` too.
+#[cfg(feature = "sync")]
+fn get_privileged_json<T: DeserializeOwned>(
+    agent: &Agent,
+    url: &str,
+    path: &str,
+) -> Result<Option<T>, EnphaseError> {
+    call_privileged_json(agent.get(&format!("{}/{}", url, path)))
+}
+
+/// Like [`get_privileged_json`], for a request that needs more than a path.
+#[cfg(feature = "sync")]
+fn call_privileged_json<T: DeserializeOwned>(request: Request) -> Result<Option<T>, EnphaseError> {
+    match call_json(request) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if matches!(e.status(), Some(401 | 403 | 404)) => {
+            debug!("{}: not allowed or not on this Envoy, skipping", e);
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
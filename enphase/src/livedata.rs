@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use tracing::info;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_json, EnphaseError};
+
+/// Power flows at about one-second resolution, from `ivp/livedata/status` on firmware 7 and later.
+/// The meters only update while the live stream is on; see [`enable_livedata_stream`].
+#[derive(Deserialize, Debug)]
+pub struct LiveDataResponse {
+    /*
+    {
+        "connection": {
+            "mqtt_state": "connected",
+            "prov_state": "configured",
+            "auth_state": "ok",
+            "sc_stream": "enabled",
+            "sc_debug": "disabled"
+        },
+        "meters": {
+            "last_update": 1690000000,
+            "soc": 95,
+            "main_relay_state": 1,
+            "gen_relay_state": 5,
+            "backup_bat_mode": 1,
+            "backup_soc": 30,
+            "is_split_phase": 0,
+            "phase_count": 1,
+            "enc_agg_soc": 95,
+            "enc_agg_energy": 3325,
+            "acb_agg_soc": 0,
+            "acb_agg_energy": 0,
+            "pv": {"agg_p_mw": 2504000, "agg_s_mva": 2530000, "agg_p_ph_a_mw": 2504000, ...},
+            "storage": {"agg_p_mw": -1200000, "agg_s_mva": 1210000, ...},
+            "grid": {"agg_p_mw": 0, "agg_s_mva": 0, ...},
+            "load": {"agg_p_mw": 1304000, "agg_s_mva": 1320000, ...},
+            "generator": {"agg_p_mw": 0, "agg_s_mva": 0, ...}
+        },
+        "tasks": {...},
+        "counters": {...}
+    }
+     */
+    pub connection: LiveDataConnection,
+    pub meters: LiveDataMeters,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LiveDataConnection {
+    /// "enabled" or "disabled"
+    pub sc_stream: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LiveDataMeters {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub last_update: DateTime<Utc>,
+    /// battery state of charge, 0-100
+    pub soc: Option<i32>,
+    pub pv: LiveDataMeter,
+    /// positive when the batteries are discharging
+    pub storage: LiveDataMeter,
+    /// positive when importing from the grid
+    pub grid: LiveDataMeter,
+    pub load: LiveDataMeter,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LiveDataMeter {
+    /// mW
+    pub agg_p_mw: i64,
+    /// mVA
+    pub agg_s_mva: i64,
+}
+
+#[cfg(feature = "sync")]
+pub fn get_livedata(agent: &Agent, url: &str) -> Result<LiveDataResponse, EnphaseError> {
+    get_json(agent, url, "ivp/livedata/status")
+}
+
+/// Turns on the live stream, without which `ivp/livedata/status` keeps reporting the last values
+/// (or zeros). The Envoy turns it off again on its own after a few minutes.
+#[cfg(feature = "sync")]
+pub fn enable_livedata_stream(agent: &Agent, url: &str) -> Result<(), EnphaseError> {
+    info!("turning on the live stream at {}", url);
+    agent
+        .post(&format!("{}/ivp/livedata/stream", url))
+        .send_json(serde_json::json!({ "enable": 1 }))?;
+    Ok(())
+}
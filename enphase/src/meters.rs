@@ -0,0 +1,327 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_optional_json, get_privileged_json, EnphaseError};
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterConfigResponse {
+    /*
+    [
+        {
+            "eid": 704643328,
+            "state": "enabled",
+            "measurementType": "production",
+            "phaseMode": "split",
+            "phaseCount": 2,
+            "meteringStatus": "normal",
+            "statusFlags": []
+        },
+        {
+            "eid": 704643584,
+            "state": "enabled",
+            "measurementType": "net-consumption",
+            "phaseMode": "split",
+            "phaseCount": 2,
+            "meteringStatus": "not-metering",
+            "statusFlags": ["production-imbalance"]
+        }
+    ]
+     */
+    pub eid: u64,
+    /// `enabled` or `disabled`
+    pub state: String,
+    /// `production`, `net-consumption`, or `total-consumption`
+    pub measurement_type: String,
+    pub phase_mode: String,
+    pub phase_count: i32,
+    /// `normal`, `not-metering`, or `check-wiring`
+    pub metering_status: String,
+    pub status_flags: Vec<String>,
+}
+
+/// How one current transformer is installed, from `ivp/meters/cts`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CtConfigResponse {
+    /*
+    [
+        {
+            "eid": 704643328,
+            "measurementType": "production",
+            "phase": "ph-a",
+            "polarity": "normal",
+            "verified": true
+        },
+        {
+            "eid": 704643584,
+            "measurementType": "net-consumption",
+            "phase": "ph-b",
+            "polarity": "reversed",
+            "verified": true
+        }
+    ]
+     */
+    /// the meter in `ivp/meters` it belongs to
+    pub eid: u64,
+    pub measurement_type: String,
+    /// `ph-a`, `ph-b`, or `ph-c`
+    pub phase: Option<String>,
+    /// `normal` or `reversed`, as found when the installer verified the wiring
+    pub polarity: Option<String>,
+    pub verified: Option<bool>,
+}
+
+/// Lists the CTs; empty without installer credentials, or on Envoys without any.
+#[cfg(feature = "sync")]
+pub fn get_ct_config(agent: &Agent, url: &str) -> Result<Vec<CtConfigResponse>, EnphaseError> {
+    Ok(get_privileged_json(agent, url, "ivp/meters/cts")?.unwrap_or_default())
+}
+
+/// Lists the Envoy's configured meters; empty on Envoys that have none.
+#[cfg(feature = "sync")]
+pub fn get_meter_config(
+    agent: &Agent,
+    url: &str,
+) -> Result<Vec<MeterConfigResponse>, EnphaseError> {
+    Ok(get_optional_json(agent, url, "ivp/meters")?.unwrap_or_default())
+}
+
+/// Instantaneous readings for one meter, from `ivp/meters/readings`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterReadingResponse {
+    /*
+    [
+        {
+            "eid": 704643328,
+            "timestamp": 1688000000,
+            "actEnergyDlvd": 1234.5,
+            "actEnergyRcvd": 0.1,
+            "activePower": 2301.2,
+            "apparentPower": 2350.7,
+            "reactivePower": -120.3,
+            "pwrFactor": 0.98,
+            "voltage": 240.4,
+            "current": 9.8,
+            "freq": 60.0,
+            "channels": [
+                {"eid": 1778385169, "timestamp": 1688000000, "activePower": 1150.6, ..., "freq": 60.0},
+                {"eid": 1778385170, "timestamp": 1688000000, "activePower": 1150.6, ..., "freq": 60.0},
+                {"eid": 1778385171, "timestamp": 1688000000, "activePower": 0.0, ..., "voltage": 0.0, ...}
+            ]
+        }
+    ]
+     */
+    pub eid: u64,
+    #[serde(flatten)]
+    pub reading: MeterReading,
+    /// One entry per phase. Split- and single-phase Envoys still report three, with the unused
+    /// ones zeroed.
+    #[serde(default)]
+    pub channels: Vec<MeterReading>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterReading {
+    pub active_power: f64,
+    pub reactive_power: f64,
+    pub apparent_power: f64,
+    pub voltage: f64,
+    pub current: f64,
+    pub freq: f64,
+    pub pwr_factor: f64,
+}
+
+/// Empty on Envoys without meters.
+#[cfg(feature = "sync")]
+pub fn get_meter_readings(
+    agent: &Agent,
+    url: &str,
+) -> Result<Vec<MeterReadingResponse>, EnphaseError> {
+    Ok(get_optional_json(agent, url, "ivp/meters/readings")?.unwrap_or_default())
+}
+
+/// What the house is producing, using, and trading with the grid right now, from its meters.
+#[derive(Debug, PartialEq)]
+pub struct MetersResponse {
+    pub production_watts: f64,
+    pub consumption_watts: f64,
+    /// drawn from the grid; negative while exporting
+    pub net_watts: f64,
+    /// how the consumption CT is installed: `net-consumption` or `total-consumption`
+    pub measurement_type: String,
+}
+
+impl MetersResponse {
+    /// Works out the house's power from its meters' readings, whichever way the consumption
+    /// CT is installed. `None` without an enabled consumption meter.
+    pub fn new(
+        config: &[MeterConfigResponse],
+        readings: &[MeterReadingResponse],
+    ) -> Option<MetersResponse> {
+        let watts = |measurement_type: &str| {
+            let meter = config
+                .iter()
+                .find(|m| m.state == "enabled" && m.measurement_type == measurement_type)?;
+            readings
+                .iter()
+                .find(|r| r.eid == meter.eid)
+                .map(|r| r.reading.active_power)
+        };
+        let production_watts = watts("production").unwrap_or_default();
+        if let Some(net_watts) = watts("net-consumption") {
+            Some(MetersResponse {
+                production_watts,
+                consumption_watts: production_watts + net_watts,
+                net_watts,
+                measurement_type: "net-consumption".to_string(),
+            })
+        } else {
+            watts("total-consumption").map(|consumption_watts| MetersResponse {
+                production_watts,
+                consumption_watts,
+                net_watts: consumption_watts - production_watts,
+                measurement_type: "total-consumption".to_string(),
+            })
+        }
+    }
+}
+
+/// Reads the meters; `None` on systems without a consumption CT.
+#[cfg(feature = "sync")]
+pub fn get_meters(agent: &Agent, url: &str) -> Result<Option<MetersResponse>, EnphaseError> {
+    let config = get_meter_config(agent, url)?;
+    if config.is_empty() {
+        return Ok(None);
+    }
+    let readings = get_meter_readings(agent, url)?;
+    Ok(MetersResponse::new(&config, &readings))
+}
+
+/// One report from `ivp/meters/reports/consumption`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumptionReportResponse {
+    /*
+    [
+        {
+            "createdAt": 1688000000,
+            "reportType": "total-consumption",
+            "cumulative": {
+                "currW": 2001.2,
+                "actPower": 2001.2,
+                "apprntPwr": 2200.1,
+                "reactPwr": 300.0,
+                "whDlvdCum": 123456.7,
+                "whRcvdCum": 0.0,
+                "varhLagCum": 1000.0,
+                "varhLeadCum": 10.0,
+                "vahCum": 130000.0,
+                "rmsVoltage": 240.1,
+                "rmsCurrent": 9.1,
+                "pwrFactor": 0.91,
+                "freqHz": 60.0
+            },
+            "lines": [
+                {"currW": 1000.6, ..., "freqHz": 60.0},
+                {"currW": 1000.6, ..., "freqHz": 60.0}
+            ]
+        },
+        {"createdAt": 1688000000, "reportType": "net-consumption", "cumulative": {...}, "lines": [...]}
+    ]
+     */
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    /// `total-consumption` or `net-consumption`
+    pub report_type: String,
+    pub cumulative: ConsumptionReport,
+    /// One entry per phase: one on single-phase systems, up to three otherwise.
+    #[serde(default)]
+    pub lines: Vec<ConsumptionReport>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumptionReport {
+    pub curr_w: f64,
+    pub act_power: f64,
+    pub apprnt_pwr: f64,
+    pub react_pwr: f64,
+    pub wh_dlvd_cum: f64,
+    pub wh_rcvd_cum: f64,
+    pub rms_voltage: f64,
+    pub rms_current: f64,
+    pub pwr_factor: f64,
+    pub freq_hz: f64,
+}
+
+/// Empty on Envoys without consumption CTs.
+#[cfg(feature = "sync")]
+pub fn get_consumption_reports(
+    agent: &Agent,
+    url: &str,
+) -> Result<Vec<ConsumptionReportResponse>, EnphaseError> {
+    Ok(get_optional_json(agent, url, "ivp/meters/reports/consumption")?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(measurement_type: &str) -> Vec<MeterConfigResponse> {
+        serde_json::from_str(&format!(
+            r#"[
+                {{"eid": 1, "state": "enabled", "measurementType": "production", "phaseMode": "split", "phaseCount": 2, "meteringStatus": "normal", "statusFlags": []}},
+                {{"eid": 2, "state": "enabled", "measurementType": "{}", "phaseMode": "split", "phaseCount": 2, "meteringStatus": "normal", "statusFlags": []}}
+            ]"#,
+            measurement_type
+        ))
+        .unwrap()
+    }
+
+    fn readings(production: f64, consumption: f64) -> Vec<MeterReadingResponse> {
+        serde_json::from_str(&format!(
+            r#"[
+                {{"eid": 1, "activePower": {}, "reactivePower": 0, "apparentPower": 0, "voltage": 240, "current": 0, "freq": 60, "pwrFactor": 1}},
+                {{"eid": 2, "activePower": {}, "reactivePower": 0, "apparentPower": 0, "voltage": 240, "current": 0, "freq": 60, "pwrFactor": 1}}
+            ]"#,
+            production, consumption
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn net_consumption_meter() {
+        let meters = MetersResponse::new(&config("net-consumption"), &readings(3000.0, -1800.0));
+        assert_eq!(
+            meters,
+            Some(MetersResponse {
+                production_watts: 3000.0,
+                consumption_watts: 1200.0,
+                net_watts: -1800.0,
+                measurement_type: "net-consumption".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn total_consumption_meter() {
+        let meters = MetersResponse::new(&config("total-consumption"), &readings(3000.0, 1200.0));
+        assert_eq!(meters.unwrap().net_watts, -1800.0);
+    }
+
+    #[test]
+    fn no_consumption_meter_is_none() {
+        let mut config = config("net-consumption");
+        config[1].state = "disabled".to_string();
+        assert_eq!(
+            MetersResponse::new(&config, &readings(3000.0, -1800.0)),
+            None
+        );
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(feature = "sync")]
+use std::time::Duration;
+
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{call_privileged_json, EnphaseError};
+
+/// The network checks from `admin/lib/network_display.json`, which the Envoy runs when asked:
+/// whether it can reach its gateway, resolve names, and connect to Enlighten.
+#[derive(Deserialize, Debug)]
+pub struct NetworkDisplayResponse {
+    /*
+    {
+        "gateway": "192.168.1.1",
+        "dns_servers": ["192.168.1.1", "8.8.8.8"],
+        "gateway_reachable": true,
+        "dns_reachable": true,
+        "cloud_reachable": false
+    }
+     */
+    pub gateway: Option<String>,
+    #[serde(default)]
+    pub dns_servers: Vec<String>,
+    #[serde(alias = "gateway_ping")]
+    pub gateway_reachable: Option<bool>,
+    #[serde(alias = "dns_lookup")]
+    pub dns_reachable: Option<bool>,
+    #[serde(alias = "cloud_connection")]
+    pub cloud_reachable: Option<bool>,
+}
+
+/// The checks take a while, so this gets its own `timeout` for the whole request. `None` on
+/// firmware without it, or without installer credentials.
+#[cfg(feature = "sync")]
+pub fn get_network_display(
+    agent: &Agent,
+    url: &str,
+    timeout: Duration,
+) -> Result<Option<NetworkDisplayResponse>, EnphaseError> {
+    let request = agent
+        .get(&format!("{}/admin/lib/network_display.json", url))
+        .timeout(timeout);
+    call_privileged_json(request)
+}
@@ -0,0 +1,42 @@
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_privileged_json, EnphaseError};
+
+/// The progress of a device scan from `ivp/peb/newscan`, which the Envoy runs while it's being
+/// commissioned or panels are added. Between scans it still answers, with nothing running.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NewScanResponse {
+    /*
+    {
+        "newDeviceScan": {
+            "prevScanTime": 1690000000,
+            "inProgress": true,
+            "newPcuCount": 2,
+            "totalPcuCount": 12
+        }
+    }
+     */
+    pub new_device_scan: NewDeviceScan,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NewDeviceScan {
+    #[serde(default)]
+    pub in_progress: bool,
+    /// microinverters found by this scan
+    #[serde(default)]
+    pub new_pcu_count: i64,
+    /// microinverters the Envoy knows about
+    pub total_pcu_count: Option<i64>,
+}
+
+/// `None` on firmware without the endpoint, or without installer credentials.
+#[cfg(feature = "sync")]
+pub fn get_new_scan(agent: &Agent, url: &str) -> Result<Option<NewScanResponse>, EnphaseError> {
+    get_privileged_json(agent, url, "ivp/peb/newscan")
+}
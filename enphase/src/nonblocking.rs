@@ -0,0 +1,177 @@
+//! Async versions of the main requests, on `reqwest`, for callers that already run an async
+//! runtime or want to fetch from several Envoys at once. Send the `Authorization` header with
+//! [`EnvoyAuth::envoy_auth`]; unlike [`crate::EnvoyClient`], nothing here retries or
+//! re-authenticates.
+
+use reqwest::{
+    header::{AUTHORIZATION, WWW_AUTHENTICATE},
+    Client, RequestBuilder, StatusCode,
+};
+use serde::de::DeserializeOwned;
+use tracing::{debug, info};
+
+use crate::{
+    auth::answer_challenge, home::HomeResponse, inverters::InvertersResponse, EnphaseError,
+};
+
+/// Sends the Envoy's `Authorization` header with a request, as the blocking client's middleware
+/// does.
+pub trait EnvoyAuth {
+    /// `authorization` is the value from [`get_auth_header`], or `Bearer <token>`.
+    fn envoy_auth(self, authorization: &str) -> Self;
+}
+
+impl EnvoyAuth for RequestBuilder {
+    fn envoy_auth(self, authorization: &str) -> Self {
+        self.header(AUTHORIZATION, authorization)
+    }
+}
+
+/// Negotiates digest auth with the Envoy and returns the `Authorization` header value to send
+/// with every subsequent request.
+#[tracing::instrument(level = "debug", skip(client, password))]
+pub async fn get_auth_header(
+    client: &Client,
+    url: &str,
+    username: &str,
+    password: &str,
+) -> Result<String, EnphaseError> {
+    let setup_url = format!("{}/installer/setup/home", url);
+    info!("negotiating digest auth with {}", url);
+    let response = client.get(&setup_url).send().await?;
+    if response.status() != StatusCode::UNAUTHORIZED {
+        return Err(EnphaseError::Auth(format!(
+            "expected a 401 challenge from {} but got status {}",
+            setup_url,
+            response.status().as_u16()
+        )));
+    }
+    let challenge = response
+        .headers()
+        .get(WWW_AUTHENTICATE)
+        .and_then(|h| h.to_str().ok())
+        .ok_or_else(|| {
+            EnphaseError::Auth(format!(
+                "{} did not send a WWW-Authenticate header",
+                setup_url
+            ))
+        })?;
+    debug!("{} challenged with {}", setup_url, challenge);
+    answer_challenge(challenge, username, password)
+}
+
+/// GETs `<url>/<path>` with `authorization` and decodes the JSON body.
+async fn get_json<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    path: &str,
+    authorization: &str,
+) -> Result<T, EnphaseError> {
+    let full_url = format!("{}/{}", url, path);
+    debug!("GET {}", full_url);
+    let response = client
+        .get(&full_url)
+        .envoy_auth(authorization)
+        .send()
+        .await?
+        .error_for_status()?;
+    let status = response.status();
+    let body = response.text().await?;
+    debug!(
+        "{} answered {} with {} bytes",
+        full_url,
+        status.as_u16(),
+        body.len()
+    );
+    serde_json::from_str(&body).map_err(|source| {
+        debug!("couldn't parse {}: {}", full_url, body);
+        EnphaseError::Deserialize {
+            url: full_url,
+            source,
+        }
+    })
+}
+
+#[tracing::instrument(level = "debug", skip(client, authorization))]
+pub async fn get_home(
+    client: &Client,
+    url: &str,
+    authorization: &str,
+) -> Result<HomeResponse, EnphaseError> {
+    get_json(client, url, "home.json", authorization).await
+}
+
+#[tracing::instrument(level = "debug", skip(client, authorization))]
+pub async fn get_inverters(
+    client: &Client,
+    url: &str,
+    authorization: &str,
+) -> Result<Vec<InvertersResponse>, EnphaseError> {
+    get_json(client, url, "api/v1/production/inverters", authorization).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+
+    /// Answers one request with `status` and `body`, handing back the request's headers.
+    fn envoy(status: &str, body: &'static str) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let status = status.to_string();
+        let handle = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let headers: Vec<String> = BufReader::new(&socket)
+                .lines()
+                .map(Result::unwrap)
+                .take_while(|line| !line.is_empty())
+                .collect();
+            write!(
+                socket,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+            headers
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn inverters_are_fetched_with_the_authorization() {
+        let (url, envoy) = envoy(
+            "200 OK",
+            r#"[{"serialNumber": "123456789012", "lastReportDate": 1688000000, "lastReportWatts": 123, "maxReportWatts": 234}]"#,
+        );
+        let inverters = get_inverters(&Client::new(), &url, "Bearer abc")
+            .await
+            .unwrap();
+        assert_eq!(inverters[0].serial_number, "123456789012");
+        let headers = envoy.join().unwrap();
+        assert!(
+            headers
+                .iter()
+                .any(|h| h.eq_ignore_ascii_case("authorization: Bearer abc")),
+            "{:?}",
+            headers
+        );
+    }
+
+    #[tokio::test]
+    async fn status_errors_keep_their_status() {
+        let (url, _envoy) = envoy("404 Not Found", "");
+        let error = get_home(&Client::new(), &url, "Bearer abc")
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), Some(404));
+        assert!(!error.is_retriable());
+    }
+}
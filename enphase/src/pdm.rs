@@ -0,0 +1,164 @@
+#[cfg(feature = "sync")]
+use std::collections::BTreeMap;
+
+#[cfg(feature = "sync")]
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_json, get_optional_json, EnphaseError};
+
+/// One device from `ivp/pdm/device_data`, an installer-only view of what each microinverter
+/// last reported, in much more detail than `api/v1/production/inverters`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PdmDevice {
+    /*
+    {
+        "1073741824": {
+            "devName": "pcu",
+            "sn": "121234567890",
+            "active": true,
+            "modGone": false,
+            "channels": [
+                {
+                    "chanEid": 1627390225,
+                    "created": 1690000000,
+                    "wattHours": {"today": 1234, "yesterday": 2345, "week": 12345},
+                    "watts": {"now": 250, "nowUsed": 0, "max": 300},
+                    "lifetime": {"createdTime": 1601234567, "joulesProduced": 12345678900},
+                    "lastReading": {
+                        "eid": 1627390225,
+                        "interval_type": 0,
+                        "endDate": 1690000000,
+                        "duration": 900,
+                        "joulesProduced": 225000,
+                        "acVoltageINmV": 240123,
+                        "acFrequencyINmHz": 60001,
+                        "dcVoltageINmV": 35000,
+                        "dcCurrentINmA": 7000,
+                        "channelTemp": 35,
+                        ...
+                    }
+                }
+            ]
+        },
+        "deviceCount": 1,
+        "deviceDataLimit": 50
+    }
+     */
+    /// `pcu` for a microinverter
+    pub dev_name: String,
+    pub sn: String,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub channels: Vec<PdmChannel>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PdmChannel {
+    pub watts: Option<PdmWatts>,
+    pub lifetime: Option<PdmLifetime>,
+    pub last_reading: Option<PdmReading>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PdmWatts {
+    pub now: i64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PdmLifetime {
+    pub joules_produced: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PdmReading {
+    /// epoch seconds
+    #[serde(rename = "endDate")]
+    pub end_date: i64,
+    #[serde(rename = "acVoltageINmV")]
+    pub ac_voltage_mv: i64,
+    #[serde(rename = "acFrequencyINmHz")]
+    pub ac_frequency_mhz: i64,
+    #[serde(rename = "dcVoltageINmV")]
+    pub dc_voltage_mv: i64,
+    #[serde(rename = "dcCurrentINmA")]
+    pub dc_current_ma: i64,
+    /// °C
+    #[serde(rename = "channelTemp")]
+    pub channel_temp: i64,
+}
+
+/// The response is keyed by EID, alongside a few counts that aren't devices.
+#[cfg(feature = "sync")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DeviceDataEntry {
+    Device(PdmDevice),
+    Other(IgnoredAny),
+}
+
+#[cfg(feature = "sync")]
+pub fn get_device_data(agent: &Agent, url: &str) -> Result<Vec<PdmDevice>, EnphaseError> {
+    let entries: BTreeMap<String, DeviceDataEntry> = get_json(agent, url, "ivp/pdm/device_data")?;
+    Ok(entries
+        .into_values()
+        .filter_map(|entry| match entry {
+            DeviceDataEntry::Device(device) => Some(device),
+            DeviceDataEntry::Other(_) => None,
+        })
+        .collect())
+}
+
+/// The energy totals from `ivp/pdm/energy`, keyed by inverter serial number.
+#[cfg(feature = "sync")]
+#[derive(Deserialize, Debug)]
+struct PdmEnergyResponse {
+    /*
+    {
+        "production": {
+            "pcu": {
+                "121234567890": {
+                    "wattHoursToday": 1234,
+                    "wattHoursSevenDays": 12345,
+                    "wattHoursLifetime": 1234567,
+                    "wattsNow": 250
+                },
+                ...
+            }
+        }
+    }
+     */
+    production: PdmEnergyProduction,
+}
+
+#[cfg(feature = "sync")]
+#[derive(Deserialize, Debug)]
+struct PdmEnergyProduction {
+    #[serde(default)]
+    pcu: BTreeMap<String, InverterEnergy>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InverterEnergy {
+    pub watt_hours_today: i64,
+    pub watt_hours_seven_days: i64,
+    pub watt_hours_lifetime: i64,
+}
+
+/// Each inverter's energy totals by serial number; empty on Envoys without the endpoint.
+#[cfg(feature = "sync")]
+pub fn get_inverter_energy(
+    agent: &Agent,
+    url: &str,
+) -> Result<BTreeMap<String, InverterEnergy>, EnphaseError> {
+    let energy: Option<PdmEnergyResponse> = get_optional_json(agent, url, "ivp/pdm/energy")?;
+    Ok(energy.map(|e| e.production.pcu).unwrap_or_default())
+}
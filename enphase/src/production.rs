@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_json, EnphaseError};
+
+#[derive(Deserialize, Debug)]
+pub struct ProductionResponse {
+    /*
+    {
+    "production":[
+        {"type":"inverters","activeCount":10,"readingTime":1688000000,"wNow":225,"whLifetime":2935},
+        {
+            "type":"eim",
+            "activeCount":1,
+            "measurementType":"production",
+            "readingTime":1688000000,
+            "wNow":230.2,
+            "whLifetime":3012.1,
+            "rmsCurrent":1.9,
+            "rmsVoltage":241.3,
+            "reactPwr":12.0,
+            "apprntPwr":460.1,
+            "pwrFactor":0.5,
+            "whToday":1500.0,
+            "whLastSevenDays":12000.0,
+            "lines":[
+                {"wNow":115.1,"whLifetime":1506.0,"rmsCurrent":0.95,"rmsVoltage":241.3,"reactPwr":6.0,"apprntPwr":230.0,"pwrFactor":0.5,"whToday":750.0,"whLastSevenDays":6000.0, ...},
+                {"wNow":115.1, ...}
+            ]
+        }
+    ],
+    "consumption":[
+        {"type":"eim","activeCount":1,"measurementType":"total-consumption", ...same fields as above...},
+        {"type":"eim","activeCount":1,"measurementType":"net-consumption", ...}
+    ],
+    "storage":[{"type":"acb","activeCount":0,"readingTime":0,"wNow":0,"whNow":0,"state":"idle"}]
+    }
+     */
+    #[serde(default)]
+    pub production: Vec<ProductionEntry>,
+
+    /// Empty or absent on Envoys without consumption CTs.
+    #[serde(default)]
+    pub consumption: Vec<ProductionEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ProductionEntry {
+    /// Totals reported by the microinverters themselves.
+    Inverters(InvertersProduction),
+    /// Readings from the Envoy's integrated meter: the production CT or a consumption CT.
+    Eim(EimProduction),
+    /// Any other device type (firmware has been known to add some).
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InvertersProduction {
+    pub active_count: i32,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub reading_time: DateTime<Utc>,
+    pub w_now: f64,
+    pub wh_lifetime: f64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EimProduction {
+    /// Number of CTs feeding this reading; 0 means the meter isn't installed and every value is 0.
+    pub active_count: i32,
+    /// `production`, `total-consumption`, or `net-consumption`
+    pub measurement_type: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub reading_time: DateTime<Utc>,
+    pub w_now: f64,
+    pub wh_lifetime: f64,
+    pub wh_today: f64,
+    pub wh_last_seven_days: f64,
+    pub rms_current: f64,
+    pub rms_voltage: f64,
+    pub react_pwr: f64,
+    pub apprnt_pwr: f64,
+    pub pwr_factor: f64,
+    /// The same readings per phase, L1 first: one line on a single-phase system, up to three on a
+    /// three-phase one.
+    #[serde(default)]
+    pub lines: Vec<EimLine>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EimLine {
+    pub w_now: f64,
+    pub wh_lifetime: f64,
+    pub wh_today: f64,
+    pub wh_last_seven_days: f64,
+    pub rms_current: f64,
+    pub rms_voltage: f64,
+    pub react_pwr: f64,
+    pub apprnt_pwr: f64,
+    pub pwr_factor: f64,
+}
+
+/// Asks for the details, which add the per-phase [`EimLine`]s.
+#[cfg(feature = "sync")]
+pub fn get_production(agent: &Agent, url: &str) -> Result<ProductionResponse, EnphaseError> {
+    get_json(agent, url, "production.json?details=1")
+}
+
+/// The whole-array summary from `api/v1/production`, available even on Envoys without meters.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductionSummaryResponse {
+    /*
+    {
+    "wattHoursToday": 21674,
+    "wattHoursSevenDays": 719360,
+    "wattHoursLifetime": 20305126,
+    "wattsNow": 2279
+    }
+     */
+    pub watt_hours_today: i64,
+    pub watt_hours_seven_days: i64,
+    pub watt_hours_lifetime: i64,
+    pub watts_now: i64,
+}
+
+#[cfg(feature = "sync")]
+pub fn get_production_summary(
+    agent: &Agent,
+    url: &str,
+) -> Result<ProductionSummaryResponse, EnphaseError> {
+    get_json(agent, url, "api/v1/production")
+}
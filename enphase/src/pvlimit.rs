@@ -0,0 +1,34 @@
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_privileged_json, EnphaseError};
+
+/// The export limit from `ivp/sc/pvlimit`, for utilities that cap how much the system may send
+/// to the grid.
+#[derive(Deserialize, Debug)]
+pub struct PvLimitResponse {
+    /*
+    {
+        "enabled": true,
+        "limit_w": 5000,
+        "active": false
+    }
+     */
+    /// whether a limit is configured at all
+    #[serde(default)]
+    pub enabled: bool,
+    /// watts
+    #[serde(alias = "limit")]
+    pub limit_w: Option<f64>,
+    /// whether the inverters are being curtailed right now
+    #[serde(default)]
+    pub active: bool,
+}
+
+/// `None` on firmware without export limiting, or without installer credentials.
+#[cfg(feature = "sync")]
+pub fn get_pv_limit(agent: &Agent, url: &str) -> Result<Option<PvLimitResponse>, EnphaseError> {
+    get_privileged_json(agent, url, "ivp/sc/pvlimit")
+}
@@ -0,0 +1,32 @@
+use std::{thread, time::Duration};
+
+use tracing::warn;
+
+use crate::EnphaseError;
+
+/// Calls `f` until it succeeds, fails with an error that isn't [`EnphaseError::is_retriable`], or
+/// has been called `max_attempts` times. The wait before each retry starts at `base_delay` and
+/// doubles every time, less up to half at random so that clients started together don't keep
+/// retrying together.
+pub fn retry<F, T>(mut f: F, max_attempts: u32, base_delay: Duration) -> Result<T, EnphaseError>
+where
+    F: FnMut() -> Result<T, EnphaseError>,
+{
+    let mut delay = base_delay;
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(e) if e.is_retriable() && attempt < max_attempts => {
+                let wait = delay.mul_f64(1.0 - fastrand::f64() / 2.0);
+                warn!(
+                    "attempt {} of {} failed, retrying in {:?}: {}",
+                    attempt, max_attempts, wait, e
+                );
+                thread::sleep(wait);
+                delay = delay.saturating_mul(2);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
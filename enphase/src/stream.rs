@@ -0,0 +1,99 @@
+use std::io::{BufRead, BufReader, Lines, Read};
+
+use serde::Deserialize;
+use tracing::{debug, info};
+use ureq::Agent;
+
+use crate::EnphaseError;
+
+/// One server-sent event from `stream/meter`: the meters' readings, per phase, a few times a
+/// second.
+#[derive(Deserialize, Debug)]
+pub struct MeterStreamEvent {
+    /*
+    data: {
+        "production": {
+            "ph-a": {"p": 1234.5, "q": 120.1, "s": 1250.3, "v": 121.2, "i": 10.3, "pf": 0.99, "f": 60.0},
+            "ph-b": {"p": 1230.1, "q": 119.8, "s": 1246.7, "v": 120.9, "i": 10.3, "pf": 0.99, "f": 60.0},
+            "ph-c": {"p": 0.0, "q": 0.0, "s": 0.0, "v": 0.0, "i": 0.0, "pf": 0.0, "f": 0.0}
+        },
+        "net-consumption": {"ph-a": {...}, "ph-b": {...}, "ph-c": {...}},
+        "total-consumption": {"ph-a": {...}, "ph-b": {...}, "ph-c": {...}}
+    }
+     */
+    pub production: MeterStreamPhases,
+    #[serde(rename = "net-consumption")]
+    pub net_consumption: Option<MeterStreamPhases>,
+    #[serde(rename = "total-consumption")]
+    pub total_consumption: Option<MeterStreamPhases>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MeterStreamPhases {
+    #[serde(rename = "ph-a")]
+    pub ph_a: Option<MeterStreamPhase>,
+    #[serde(rename = "ph-b")]
+    pub ph_b: Option<MeterStreamPhase>,
+    #[serde(rename = "ph-c")]
+    pub ph_c: Option<MeterStreamPhase>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MeterStreamPhase {
+    /// real power, W
+    pub p: f64,
+    /// reactive power, VAR
+    pub q: f64,
+    /// apparent power, VA
+    pub s: f64,
+    /// V
+    pub v: f64,
+    /// A
+    pub i: f64,
+    /// power factor
+    pub pf: f64,
+    /// frequency, Hz
+    pub f: f64,
+}
+
+/// The events of one `stream/meter` connection, as they arrive. Ends when the Envoy closes the
+/// connection, which it does every few minutes.
+pub struct MeterStream {
+    url: String,
+    lines: Lines<BufReader<Box<dyn Read + Send + Sync>>>,
+}
+
+impl Iterator for MeterStream {
+    type Item = Result<MeterStreamEvent, EnphaseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            // skip blank separators, comments, and any other SSE fields
+            let Some(data) = line.strip_prefix("data:") else {
+                continue;
+            };
+            return Some(serde_json::from_str(data.trim()).map_err(|source| {
+                debug!("couldn't parse an event from {}: {}", self.url, data);
+                EnphaseError::Deserialize {
+                    url: self.url.clone(),
+                    source,
+                }
+            }));
+        }
+    }
+}
+
+/// Opens `stream/meter`. Only Envoys with CT meters have it.
+pub fn stream_meter(agent: &Agent, url: &str) -> Result<MeterStream, EnphaseError> {
+    let full_url = format!("{}/stream/meter", url);
+    info!("streaming {}", full_url);
+    let response = agent.get(&full_url).call()?;
+    Ok(MeterStream {
+        url: full_url,
+        lines: BufReader::new(response.into_reader()).lines(),
+    })
+}
@@ -0,0 +1,70 @@
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_privileged_json, EnphaseError};
+
+/// The tariff and battery settings from `admin/lib/tariff`. Firmware 5 and 7 leave out different
+/// parts of it, so everything is optional.
+#[derive(Deserialize, Debug)]
+pub struct TariffResponse {
+    /*
+    {
+        "tariff": {
+            "currency": {"code": "USD"},
+            "logger": "mylogger",
+            "date": "1690000000",
+            "storage_settings": {
+                "mode": "self-consumption",
+                "operation_mode_sub_type": "",
+                "reserved_soc": 30.0,
+                "very_low_soc": 5,
+                "charge_from_grid": false,
+                "date": "1690000000"
+            },
+            "single_rate": {"rate": 0.15, "sell": 0.05},
+            "seasons": [...],
+            "seasons_sell": [...]
+        },
+        "schedule": {...}
+    }
+     */
+    pub tariff: Tariff,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Tariff {
+    pub currency: Option<TariffCurrency>,
+    pub storage_settings: Option<StorageSettings>,
+    pub single_rate: Option<SingleRate>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TariffCurrency {
+    pub code: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StorageSettings {
+    /// `self-consumption`, `savings-mode`, `backup`, ...
+    pub mode: Option<String>,
+    /// percent of the batteries kept in reserve for an outage
+    pub reserved_soc: Option<f64>,
+    pub very_low_soc: Option<f64>,
+    pub charge_from_grid: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SingleRate {
+    /// per kWh bought
+    pub rate: Option<f64>,
+    /// per kWh sold
+    pub sell: Option<f64>,
+}
+
+/// The tariff; `None` on Envoys that don't have the endpoint, or without installer credentials.
+#[cfg(feature = "sync")]
+pub fn get_tariff(agent: &Agent, url: &str) -> Result<Option<TariffResponse>, EnphaseError> {
+    get_privileged_json(agent, url, "admin/lib/tariff")
+}
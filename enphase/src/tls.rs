@@ -0,0 +1,34 @@
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier},
+    Certificate, ClientConfig, ServerName,
+};
+use std::{sync::Arc, time::SystemTime};
+
+/// Envoys serve HTTPS with a certificate they signed themselves, which no CA will vouch for.
+struct AcceptAnyCertificate;
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// A TLS configuration that skips certificate verification entirely, for talking to an Envoy over
+/// `https://`. Pass it to [`ureq::AgentBuilder::tls_config`]. This gives up protection against
+/// anyone impersonating the Envoy, so only use it on a network you trust.
+pub fn insecure_tls_config() -> Arc<ClientConfig> {
+    Arc::new(
+        ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate))
+            .with_no_client_auth(),
+    )
+}
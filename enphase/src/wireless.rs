@@ -0,0 +1,38 @@
+use serde::Deserialize;
+#[cfg(feature = "sync")]
+use ureq::Agent;
+
+#[cfg(feature = "sync")]
+use crate::{get_privileged_json, EnphaseError};
+
+/// The Wi-Fi connection from `admin/lib/wireless_display.json`, which has the signal in dBm
+/// rather than the 0 to 5 of `home.json`.
+#[derive(Deserialize, Debug)]
+pub struct WirelessDisplayResponse {
+    /*
+    {
+        "ssid": "home",
+        "channel": 6,
+        "security_mode": "WPA2-PSK",
+        "rssi": -61,
+        "status": "connected"
+    }
+     */
+    pub ssid: Option<String>,
+    pub channel: Option<i32>,
+    #[serde(alias = "security")]
+    pub security_mode: Option<String>,
+    /// dBm
+    #[serde(alias = "signal_strength")]
+    pub rssi: Option<i32>,
+    pub status: Option<String>,
+}
+
+/// `None` when the Envoy has no Wi-Fi adapter, or without installer credentials.
+#[cfg(feature = "sync")]
+pub fn get_wireless_display(
+    agent: &Agent,
+    url: &str,
+) -> Result<Option<WirelessDisplayResponse>, EnphaseError> {
+    get_privileged_json(agent, url, "admin/lib/wireless_display.json")
+}
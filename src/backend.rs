@@ -0,0 +1,11 @@
+use enphase::EnphaseError;
+
+use crate::point::Point;
+
+/// Somewhere scraped points go: stdout, a file, InfluxDB, MQTT, or StatsD.
+pub trait Backend: Send {
+    fn write(&mut self, points: &[Point]) -> Result<(), EnphaseError>;
+
+    /// Called once after the last write, for backends with something still to send.
+    fn close(self: Box<Self>) {}
+}
@@ -0,0 +1,95 @@
+use enphase::EnphaseError;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{statsd::StatsdFormat, AuthMode, LogLevel, OutputFormat};
+
+/// Shown at the end of `--help`.
+pub const CONFIG_HELP: &str = "\
+Config file:
+  --config takes a TOML file with any of the options above, using the option name with
+  underscores for dashes. Options given on the command line or through environment variables
+  take precedence. For example:
+
+    url = \"http://envoy.local\"
+    username = \"installer\"
+    password = \"hunter2\"
+    auth_mode = \"digest\"
+    interval = 60";
+
+/// Everything that can be given on the command line, loaded from a TOML file instead.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub token: Option<String>,
+    pub url: Option<String>,
+    pub auto_discover: Option<bool>,
+    pub envoy_index: Option<usize>,
+    pub auth_mode: Option<AuthMode>,
+    pub verbose: Option<u8>,
+    pub log_level: Option<LogLevel>,
+    pub envoy_serial: Option<String>,
+    pub insecure: Option<bool>,
+    pub output_format: Option<OutputFormat>,
+    pub interval: Option<u64>,
+    pub livedata: Option<bool>,
+    pub events: Option<usize>,
+    pub events_state: Option<PathBuf>,
+    pub stale_threshold_minutes: Option<i64>,
+    pub freshness: Option<i64>,
+    pub filter_serial: Option<Vec<String>>,
+    pub use_report_time: Option<bool>,
+    pub detailed_inverters: Option<bool>,
+    pub enable_ev_charger: Option<bool>,
+    pub enable_meters: Option<bool>,
+    pub enable_firmware: Option<bool>,
+    pub enable_battery: Option<bool>,
+    pub network_diagnostics: Option<bool>,
+    pub network_diagnostics_timeout_ms: Option<u64>,
+    pub stream: Option<bool>,
+    pub max_errors: Option<u32>,
+    pub timeout: Option<u64>,
+    pub connect_timeout_ms: Option<u64>,
+    pub read_timeout_ms: Option<u64>,
+    pub max_retries: Option<u32>,
+    pub retry_base_delay_ms: Option<u64>,
+    pub csv_delimiter: Option<char>,
+    pub csv_no_header: Option<bool>,
+    pub csv_single_table: Option<bool>,
+    pub mqtt_broker: Option<String>,
+    pub mqtt_topic_prefix: Option<String>,
+    pub mqtt_qos: Option<u8>,
+    pub mqtt_username: Option<String>,
+    pub mqtt_password: Option<String>,
+    pub mqtt_retained: Option<bool>,
+    pub statsd_host: Option<String>,
+    pub statsd_prefix: Option<String>,
+    pub statsd_format: Option<StatsdFormat>,
+    pub influx_url: Option<String>,
+    pub influx_org: Option<String>,
+    pub influx_bucket: Option<String>,
+    pub influx_token: Option<String>,
+    pub influx_batch_size: Option<usize>,
+    pub output_file: Option<PathBuf>,
+    pub append: Option<bool>,
+    pub also_stdout: Option<bool>,
+    pub measurement_prefix: Option<String>,
+    /// `--tag`, as a table like `tag = { site = "home" }`
+    pub tag: Option<BTreeMap<String, String>>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, EnphaseError> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            io::Error::new(e.kind(), format!("config file {}: {}", path.display(), e))
+        })?;
+        toml::from_str(&contents)
+            .map_err(|e| EnphaseError::Parse(format!("config file {}: {}", path.display(), e)))
+    }
+}
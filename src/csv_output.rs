@@ -0,0 +1,137 @@
+use std::{collections::HashMap, io};
+
+use csv::{Writer, WriterBuilder};
+use enphase::EnphaseError;
+
+use crate::point::{FieldValue, Point};
+
+/// Writes points to stdout as CSV. By default each measurement is its own section: a header row
+/// of `measurement,timestamp`, the tag names, and the field names, then one row per point. The
+/// header is only repeated when a measurement's columns change, so a daemon's output stays one
+/// table per measurement. With `single_table`, each scrape is instead flattened into one wide row
+/// with a column per measurement, tag values, and field.
+pub struct CsvWriter {
+    writer: Writer<io::Stdout>,
+    header: bool,
+    single_table: bool,
+    /// the last header written for each measurement, or for the single table under ""
+    columns: HashMap<String, Vec<String>>,
+}
+
+impl CsvWriter {
+    pub fn new(delimiter: u8, header: bool, single_table: bool) -> CsvWriter {
+        CsvWriter {
+            // sections have different numbers of columns
+            writer: WriterBuilder::new()
+                .delimiter(delimiter)
+                .flexible(true)
+                .from_writer(io::stdout()),
+            header,
+            single_table,
+            columns: HashMap::new(),
+        }
+    }
+
+    pub fn write(&mut self, points: &[Point]) -> Result<(), EnphaseError> {
+        if self.single_table {
+            self.write_single_table(points).map_err(io::Error::from)?;
+        } else {
+            self.write_sections(points).map_err(io::Error::from)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn write_sections(&mut self, points: &[Point]) -> Result<(), csv::Error> {
+        // group by measurement, keeping the order measurements are first seen in
+        let mut sections: Vec<(&str, Vec<&Point>)> = Vec::new();
+        for point in points {
+            match sections.iter_mut().find(|(m, _)| *m == point.measurement) {
+                Some((_, section)) => section.push(point),
+                None => sections.push((&point.measurement, vec![point])),
+            }
+        }
+        for (measurement, section) in sections {
+            // points of one measurement don't all have the same optional fields
+            let mut tags: Vec<&str> = Vec::new();
+            let mut fields: Vec<&str> = Vec::new();
+            for point in &section {
+                for (key, _) in &point.tags {
+                    if !tags.contains(&key.as_str()) {
+                        tags.push(key);
+                    }
+                }
+                for (key, _) in &point.fields {
+                    if !fields.contains(&key.as_str()) {
+                        fields.push(key);
+                    }
+                }
+            }
+            let mut header = vec!["measurement".to_string(), "timestamp".to_string()];
+            header.extend(tags.iter().chain(&fields).map(|c| c.to_string()));
+            self.write_header(measurement, header)?;
+            for point in section {
+                let mut row = vec![measurement.to_string(), point.timestamp_nano.to_string()];
+                row.extend(tags.iter().map(|tag| {
+                    point
+                        .tags
+                        .iter()
+                        .find(|(key, _)| key == tag)
+                        .map(|(_, value)| value.clone())
+                        .unwrap_or_default()
+                }));
+                row.extend(fields.iter().map(|field| {
+                    point
+                        .fields
+                        .iter()
+                        .find(|(key, _)| key == field)
+                        .map(|(_, value)| format_value(value))
+                        .unwrap_or_default()
+                }));
+                self.writer.write_record(&row)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_single_table(&mut self, points: &[Point]) -> Result<(), csv::Error> {
+        let Some(timestamp) = points.iter().map(|p| p.timestamp_nano).min() else {
+            return Ok(());
+        };
+        let mut header = vec!["timestamp".to_string()];
+        let mut row = vec![timestamp.to_string()];
+        for point in points {
+            let mut prefix = point.measurement.clone();
+            for (_, value) in &point.tags {
+                prefix.push('.');
+                prefix.push_str(value);
+            }
+            for (field, value) in &point.fields {
+                header.push(format!("{}.{}", prefix, field));
+                row.push(format_value(value));
+            }
+        }
+        self.write_header("", header)?;
+        self.writer.write_record(&row)
+    }
+
+    /// Writes `columns` as a header row unless it's the same as the last one for `section`.
+    fn write_header(&mut self, section: &str, columns: Vec<String>) -> Result<(), csv::Error> {
+        if self.columns.get(section) == Some(&columns) {
+            return Ok(());
+        }
+        if self.header {
+            self.writer.write_record(&columns)?;
+        }
+        self.columns.insert(section.to_string(), columns);
+        Ok(())
+    }
+}
+
+/// Like line protocol but without the quotes, which the csv crate adds where needed.
+fn format_value(value: &FieldValue) -> String {
+    match value {
+        FieldValue::Str(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,57 @@
+use std::{
+    io,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use enphase::EnphaseError;
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use tracing::debug;
+
+const SERVICE_TYPE: &str = "_enphase-envoy._tcp.local.";
+
+/// An Envoy found on the local network.
+pub struct DiscoveredEnvoy {
+    /// the mDNS service name, like `envoy._enphase-envoy._tcp.local.`
+    pub name: String,
+    pub url: String,
+}
+
+/// Browses mDNS for `_enphase-envoy._tcp` for `timeout`, returning every Envoy that answered in
+/// the order they did.
+pub fn discover_envoys(timeout: Duration) -> Result<Vec<DiscoveredEnvoy>, EnphaseError> {
+    let mdns = ServiceDaemon::new().map_err(io::Error::other)?;
+    let events = mdns.browse(SERVICE_TYPE).map_err(io::Error::other)?;
+    let deadline = Instant::now() + timeout;
+    let mut envoys: Vec<DiscoveredEnvoy> = Vec::new();
+    while let Ok(event) = events.recv_deadline(deadline) {
+        let ServiceEvent::ServiceResolved(service) = event else {
+            continue;
+        };
+        if envoys.iter().any(|envoy| envoy.name == service.fullname) {
+            continue;
+        }
+        // an IPv4 address makes for a friendlier URL, when there is one
+        let mut addresses: Vec<IpAddr> = service.addresses.iter().map(|a| a.to_ip_addr()).collect();
+        addresses.sort_by_key(|address| address.is_ipv6());
+        let Some(address) = addresses.first() else {
+            continue;
+        };
+        let host = match address {
+            IpAddr::V4(v4) => v4.to_string(),
+            IpAddr::V6(v6) => format!("[{}]", v6),
+        };
+        let url = match service.port {
+            80 => format!("http://{}", host),
+            443 => format!("https://{}", host),
+            port => format!("http://{}:{}", host, port),
+        };
+        debug!("found {} at {}", service.fullname, url);
+        envoys.push(DiscoveredEnvoy {
+            name: service.fullname.clone(),
+            url,
+        });
+    }
+    let _ = mdns.shutdown();
+    Ok(envoys)
+}
@@ -0,0 +1,53 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use enphase::EnphaseError;
+
+use crate::{backend::Backend, point::Point};
+
+/// Writes line protocol to a file, for Telegraf's `file` or `tail` inputs. Each measurement's lines
+/// are flushed as soon as they're all written, so a reader following the file sees whole groups.
+pub struct FileBackend {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl FileBackend {
+    /// Starts the file over unless `append`.
+    pub fn open(path: &Path, append: bool) -> Result<FileBackend, EnphaseError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .map_err(|e| with_path(path, e))?;
+        Ok(FileBackend {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+        })
+    }
+}
+
+impl Backend for FileBackend {
+    fn write(&mut self, points: &[Point]) -> Result<(), EnphaseError> {
+        for (i, point) in points.iter().enumerate() {
+            writeln!(self.writer, "{}", point).map_err(|e| with_path(&self.path, e))?;
+            let last_of_measurement = points
+                .get(i + 1)
+                .is_none_or(|next| next.measurement != point.measurement);
+            if last_of_measurement {
+                self.writer.flush().map_err(|e| with_path(&self.path, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Like "out.txt: No space left on device".
+fn with_path(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
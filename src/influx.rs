@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use enphase::EnphaseError;
+use tracing::debug;
+use ureq::{Agent, AgentBuilder};
+
+use crate::{backend::Backend, point::Point};
+
+/// Writes to an InfluxDB v2 bucket through the HTTP API.
+pub struct InfluxV2Backend {
+    url: String,
+    token: String,
+    org: String,
+    bucket: String,
+    /// most lines to send in one request
+    batch_size: usize,
+    agent: Agent,
+}
+
+impl InfluxV2Backend {
+    /// Gives up on an InfluxDB that doesn't connect or answer within `timeout`, so a hung one
+    /// fails the write instead of stalling every scrape after it.
+    pub fn new(
+        url: &str,
+        token: &str,
+        org: &str,
+        bucket: &str,
+        batch_size: usize,
+        timeout: Duration,
+    ) -> InfluxV2Backend {
+        InfluxV2Backend {
+            url: url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            org: org.to_string(),
+            bucket: bucket.to_string(),
+            batch_size,
+            agent: AgentBuilder::new()
+                .timeout_connect(timeout)
+                .timeout_read(timeout)
+                .timeout_write(timeout)
+                .build(),
+        }
+    }
+
+    /// POSTs line protocol to the bucket in a single request.
+    pub fn send(&self, lines: &str) -> Result<(), EnphaseError> {
+        self.agent
+            .post(&format!("{}/api/v2/write", self.url))
+            .query("org", &self.org)
+            .query("bucket", &self.bucket)
+            .query("precision", "ns")
+            .set("Authorization", &format!("Token {}", self.token))
+            .set("Content-Type", "text/plain; charset=utf-8")
+            .send_string(lines)?;
+        Ok(())
+    }
+}
+
+impl Backend for InfluxV2Backend {
+    /// Writes the points `batch_size` at a time.
+    fn write(&mut self, points: &[Point]) -> Result<(), EnphaseError> {
+        for batch in points.chunks(self.batch_size.max(1)) {
+            let lines: Vec<String> = batch.iter().map(Point::to_string).collect();
+            debug!("writing {} lines to {}", lines.len(), self.url);
+            self.send(&lines.join("\n"))?;
+        }
+        Ok(())
+    }
+}
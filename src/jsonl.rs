@@ -0,0 +1,39 @@
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::point::{FieldValue, Point};
+
+/// Renders points as JSON Lines, for log shippers like Filebeat, Vector, or Fluent Bit. Each point
+/// is one object holding its tags and fields, plus `_measurement` and `_timestamp_ns` with the
+/// same values line protocol would have.
+pub fn to_json_lines(points: &[Point]) -> String {
+    let mut out = String::new();
+    for point in points {
+        // strings and numbers always serialize
+        out.push_str(&serde_json::to_string(&JsonLine(point)).unwrap());
+        out.push('\n');
+    }
+    out
+}
+
+struct JsonLine<'a>(&'a Point);
+
+impl Serialize for JsonLine<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let point = self.0;
+        let mut map = serializer.serialize_map(Some(2 + point.tags.len() + point.fields.len()))?;
+        map.serialize_entry("_measurement", &point.measurement)?;
+        map.serialize_entry("_timestamp_ns", &point.timestamp_nano)?;
+        for (key, value) in &point.tags {
+            map.serialize_entry(key, value)?;
+        }
+        for (key, value) in &point.fields {
+            match value {
+                FieldValue::Int(i) => map.serialize_entry(key, i)?,
+                FieldValue::Float(x) => map.serialize_entry(key, x)?,
+                FieldValue::Bool(b) => map.serialize_entry(key, b)?,
+                FieldValue::Str(s) => map.serialize_entry(key, s)?,
+            }
+        }
+        map.end()
+    }
+}
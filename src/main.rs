@@ -1,264 +1,1528 @@
-use chrono::{DateTime, TimeZone, Utc};
-use chrono_tz::{OffsetName, Tz, TZ_VARIANTS};
-use clap::Parser;
-use http_auth::PasswordClient;
-use serde::{Deserialize, Deserializer};
+use backend::Backend;
+use clap::{error::ErrorKind, ArgAction, CommandFactory, Parser, ValueEnum};
+use config::{Config, CONFIG_HELP};
+use csv_output::CsvWriter;
+use discover::discover_envoys;
+use enphase::{
+    auth::{fetch_enlighten_token, get_auth_header},
+    info::InfoResponse,
+    retry::retry,
+    tls::insecure_tls_config,
+    EnphaseError, EnvoyClient,
+};
+use file_output::FileBackend;
+use influx::InfluxV2Backend;
+use jsonl::to_json_lines;
+use mqtt::MqttBackend;
+use output::{
+    battery_power_to_influx, battery_to_influx, clock_config_to_influx,
+    consumption_reports_to_influx, consumption_summary_to_influx, ct_config_to_influx,
+    device_data_to_influx, device_scan_to_influx, dry_contacts_to_influx,
+    ensemble_inventory_to_influx, ensemble_status_to_influx, envoy_timezone, envoy_up_to_influx,
+    ev_charger_to_influx, events_to_influx, filter_inverters, firmware_to_influx, fleet_to_influx,
+    generator_config_to_influx, generator_to_influx, grid_profile_to_influx, grid_relay_to_influx,
+    home_to_influx, info_to_influx, inventory_to_influx, inverter_status_to_influx,
+    inverter_summary_to_influx, inverters_to_influx, livedata_to_influx, meter_config_to_influx,
+    meter_readings_to_influx, meter_stream_to_influx, meters_to_influx, network_display_to_influx,
+    production_summary_to_influx, production_to_influx, pv_limit_to_influx, tariff_to_influx,
+    wifi_to_influx,
+};
+use point::Point;
+use prometheus::to_prometheus;
+use serde::Deserialize;
+use statsd::{StatsdBackend, StatsdFormat};
 use std::{
-    convert::TryFrom as _,
-    time::{SystemTime, UNIX_EPOCH},
+    fs,
+    io::{self, IsTerminal},
+    path::PathBuf,
+    process::ExitCode,
+    sync::mpsc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use ureq::{builder, Agent, MiddlewareNext, Request, Response};
+use tracing::{error, info, instrument, level_filters::LevelFilter, warn};
+use tracing_subscriber::EnvFilter;
+use ureq::{builder, Agent, AgentBuilder};
+
+mod backend;
+mod config;
+mod csv_output;
+mod discover;
+mod file_output;
+mod influx;
+mod jsonl;
+mod mqtt;
+mod output;
+mod point;
+mod prometheus;
+mod statsd;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)] // Read from `Cargo.toml`
+#[command(after_long_help = CONFIG_HELP)]
 struct Cli {
-    #[arg(long, required = true)]
-    username: String,
+    /// Read options from a TOML file; see the end of `--help`
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
 
-    #[arg(long, required = true)]
-    password: String,
+    /// Log more to stderr: once for what's being fetched, twice for every request. `RUST_LOG`
+    /// overrides this
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
 
-    #[arg(long, required = true)]
-    url: String,
-}
+    /// Log at exactly this level instead of counting `-v`s. `RUST_LOG` still overrides this
+    #[arg(long, value_name = "LEVEL")]
+    log_level: Option<LogLevel>,
+
+    /// Required unless `--token` is given
+    #[arg(long, env = "ENPHASE_USERNAME")]
+    username: Option<String>,
+
+    /// Required unless `--token` is given
+    #[arg(long, env = "ENPHASE_PASSWORD", hide_env_values = true)]
+    password: Option<String>,
+
+    /// A JWT to send as `Authorization: Bearer <TOKEN>`, skipping the username/password login
+    #[arg(
+        long,
+        env = "ENPHASE_TOKEN",
+        hide_env_values = true,
+        value_name = "JWT",
+        conflicts_with = "auth_mode"
+    )]
+    token: Option<String>,
+
+    /// Base URL of the Envoy, like `http://envoy.local` (required unless `--auto-discover` is
+    /// given)
+    #[arg(long, env = "ENPHASE_URL")]
+    url: Option<String>,
+
+    /// Without `--url`, find the Envoy on the local network through mDNS
+    #[arg(long)]
+    auto_discover: bool,
+
+    /// With `--auto-discover`, which Envoy to use when more than one is found, counting from 0
+    #[arg(long, value_name = "N")]
+    envoy_index: Option<usize>,
+
+    /// How to authenticate to the Envoy [default: bearer-token if `--envoy-serial` is given,
+    /// otherwise digest]
+    #[arg(long, value_enum)]
+    auth_mode: Option<AuthMode>,
+
+    /// Serial number of the Envoy, used to fetch a token from Enlighten with `--username` and
+    /// `--password`
+    #[arg(long, visible_alias = "serial")]
+    envoy_serial: Option<String>,
+
+    /// Accept the Envoy's self-signed certificate when `--url` is `https://`
+    #[arg(long)]
+    insecure: bool,
+
+    /// How to print the scraped data [default: influx-line-protocol]
+    #[arg(long, value_enum)]
+    output_format: Option<OutputFormat>,
+
+    /// Keep running, scraping every SECONDS, instead of scraping once and exiting
+    #[arg(long, value_name = "SECONDS")]
+    interval: Option<u64>,
+
+    /// Keep `stream/meter` open and write the meters' readings as they arrive, a few times a
+    /// second, instead of scraping
+    #[arg(long, conflicts_with_all = ["interval", "livedata", "events"])]
+    stream: bool,
+
+    /// Give up on an Envoy, or InfluxDB, that doesn't connect or goes quiet for this long
+    /// [default: 30]. Envoys on weak Wi-Fi can be slow; too low a value makes requests fail and
+    /// get retried over and over.
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// Give up on connecting to the Envoy after this long, instead of `--timeout`
+    #[arg(long, value_name = "MS")]
+    connect_timeout_ms: Option<u64>,
+
+    /// Give up on a response from the Envoy that goes quiet for this long, instead of `--timeout`
+    #[arg(long, value_name = "MS")]
+    read_timeout_ms: Option<u64>,
+
+    /// How many times to retry a request that failed in a way that might be temporary, like a
+    /// dropped connection or a 5xx [default: 3]
+    #[arg(long, value_name = "N")]
+    max_retries: Option<u32>,
+
+    /// How long to wait before the first retry; each retry after that waits about twice as long
+    /// [default: 1000]
+    #[arg(long, value_name = "MS")]
+    retry_base_delay_ms: Option<u64>,
+
+    /// Fetch and parse everything once, printing a line per endpoint to stderr instead of any
+    /// output
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Scrape only the Envoy's live power flows (`ivp/livedata/status`, firmware 7 and later),
+    /// which update about once a second, instead of everything else
+    #[arg(long)]
+    livedata: bool,
+
+    /// Report the newest N entries in the Envoy's event log (`datatab/event_dt.rb`), each at the
+    /// time it was logged, instead of scraping. Events already reported are skipped
+    #[arg(long, value_name = "N", conflicts_with = "livedata")]
+    events: Option<usize>,
+
+    /// Where `--events` keeps the id of the last event it reported [default:
+    /// enphase-events.state]
+    #[arg(long, value_name = "PATH")]
+    events_state: Option<PathBuf>,
+
+    /// Count an inverter as offline once it hasn't reported for this long [default: 20]
+    #[arg(long, value_name = "MINUTES")]
+    stale_threshold_minutes: Option<i64>,
+
+    /// Count an inverter's last reading as stale once it's this old, and leave it out of
+    /// `inverter_summary`'s totals. Inverters keep their last watts after they stop reporting, like
+    /// at dusk [default: 900]
+    #[arg(long, value_name = "SECONDS")]
+    freshness: Option<i64>,
+
+    /// Timestamp each inverter's readings with when it reported them, rather than when they were
+    /// scraped
+    #[arg(long)]
+    use_report_time: bool,
+
+    /// Also report each inverter's DC and AC voltage, current, and temperature from
+    /// `ivp/pdm/device_data`, which needs installer credentials
+    #[arg(long)]
+    detailed_inverters: bool,
+
+    /// Also report the IQ EV Charger's power and session from `ivp/peb/reglsoc`
+    #[arg(long)]
+    enable_ev_charger: bool,
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-struct InvertersResponse {
-    serial_number: String,
-    #[serde(with = "chrono::serde::ts_seconds")]
-    last_report_date: DateTime<Utc>,
-    last_report_watts: i32,
-    max_report_watts: i32,
-    /*{
-    "0": {
-        "serialNumber": "123456789012",
-        "lastReportDate": 1688000000,
-        "lastReportWatts": 123,
-        "maxReportWatts": 234
-    }
-     */
+    /// Also report the Envoy's firmware version and build, and whether it's metered, as
+    /// `firmware`
+    #[arg(long)]
+    enable_firmware: bool,
+
+    /// Also report the house's production, consumption, and grid import as `meters`, on systems
+    /// with a consumption CT
+    #[arg(long)]
+    enable_meters: bool,
+
+    /// Also report each IQ Battery's capacity, rated power, and charge status in `battery`
+    #[arg(long)]
+    enable_battery: bool,
+
+    /// Also run the Envoy's network checks (`admin/lib/network_display.json`): whether it can
+    /// reach its gateway, DNS, and Enlighten
+    #[arg(long)]
+    network_diagnostics: bool,
+
+    /// How long to give `--network-diagnostics` [default: 30000]
+    #[arg(long, value_name = "MS")]
+    network_diagnostics_timeout_ms: Option<u64>,
+
+    /// Only report these inverters; repeat the option or separate serial numbers with commas
+    #[arg(long, value_name = "SERIAL", value_delimiter = ',')]
+    filter_serial: Vec<String>,
+
+    /// With `--interval`, give up after N scrapes in a row have failed [default: never]
+    #[arg(long, value_name = "N")]
+    max_errors: Option<u32>,
+
+    /// Field separator for `--output-format csv` [default: ,]
+    #[arg(long, value_name = "CHAR")]
+    csv_delimiter: Option<char>,
+
+    /// Leave the header rows out of `--output-format csv`
+    #[arg(long)]
+    csv_no_header: bool,
+
+    /// With `--output-format csv`, write one wide row per scrape instead of a table per measurement
+    #[arg(long)]
+    csv_single_table: bool,
+
+    /// Write to this InfluxDB v2 server, like `http://localhost:8086`, instead of printing
+    #[arg(long, env = "INFLUX_URL", value_name = "URL")]
+    influx_url: Option<String>,
+
+    /// Organization to write to; required with `--influx-url`
+    #[arg(long, env = "INFLUX_ORG")]
+    influx_org: Option<String>,
+
+    /// Bucket to write to; required with `--influx-url`
+    #[arg(long, env = "INFLUX_BUCKET")]
+    influx_bucket: Option<String>,
+
+    /// API token with write access to the bucket; required with `--influx-url`
+    #[arg(long, env = "INFLUX_TOKEN", hide_env_values = true)]
+    influx_token: Option<String>,
+
+    /// Most lines to send to InfluxDB in one request [default: 100]
+    #[arg(long, value_name = "LINES")]
+    influx_batch_size: Option<usize>,
+
+    /// Add this tag to every point, like `site=home`; repeat the option for more than one
+    #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = parse_tag)]
+    tags: Vec<(String, String)>,
+
+    /// Put this in front of every measurement name, like `solar_` for `solar_inverter`, to keep
+    /// several sites apart in one database
+    #[arg(long, value_name = "STR")]
+    measurement_prefix: Option<String>,
+
+    /// Write line protocol to this file or named pipe instead of printing it, like for Telegraf's
+    /// `file` input. `-` is stdout
+    #[arg(long, alias = "output", value_name = "PATH")]
+    output_file: Option<PathBuf>,
+
+    /// Add to the end of `--output-file` instead of starting it over
+    #[arg(long)]
+    append: bool,
+
+    /// Print to stdout as well as writing to `--influx-url` or `--output-file`
+    #[arg(long)]
+    also_stdout: bool,
+
+    /// Also publish every field to this MQTT broker, as `HOST[:PORT]`
+    #[arg(long, env = "MQTT_BROKER", value_name = "HOST[:PORT]")]
+    mqtt_broker: Option<String>,
+
+    /// Prefix for the MQTT topics, which are `<PREFIX>/<measurement>/<tag values...>/<field>`
+    /// [default: enphase]
+    #[arg(long, value_name = "PREFIX")]
+    mqtt_topic_prefix: Option<String>,
+
+    /// MQTT quality of service [default: 0]
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=2))]
+    mqtt_qos: Option<u8>,
+
+    #[arg(long, env = "MQTT_USERNAME")]
+    mqtt_username: Option<String>,
+
+    #[arg(long, env = "MQTT_PASSWORD", hide_env_values = true)]
+    mqtt_password: Option<String>,
+
+    /// Publish MQTT messages as retained, so new subscribers get the latest values right away
+    #[arg(long)]
+    mqtt_retained: bool,
+
+    /// Also send every numeric field to this StatsD server as a gauge; giving any `--statsd-*`
+    /// option turns this on [default: 127.0.0.1:8125]
+    #[arg(long, value_name = "HOST:PORT")]
+    statsd_host: Option<String>,
+
+    /// Prefix for the StatsD metric names, which are `<PREFIX>.<measurement>.<field>`
+    /// [default: enphase]
+    #[arg(long, value_name = "PREFIX")]
+    statsd_prefix: Option<String>,
+
+    /// How to attach tags to StatsD metrics [default: statsd]
+    #[arg(long)]
+    statsd_format: Option<StatsdFormat>,
 }
 
-#[derive(Deserialize, Debug)]
-struct HomeResponse {
-    /*
-    {
-    "software_build_epoch":1234567890,
-    "is_nonvoy":false,
-    "db_size":"12 MB",
-    "db_percent_full":"1",
-    "timezone":"America/New_York",
-    "current_date":"01/01/2023",
-    "current_time":"01:00",
-    "network":{
-        "web_comm":true,
-        "ever_reported_to_enlighten":true,
-        "last_enlighten_report_time":1234567890,
-        "primary_interface":"wlan0",
-        "interfaces":[
-            {
-                "type":"ethernet",
-                "interface":"eth0",
-                "mac":"00:11:22:33:44:55",
-                "dhcp":true,"ip":"169.169.169.169",
-                "signal_strength":0,
-                "signal_strength_max":1,
-                "carrier":false
-            },{
-                "signal_strength":1,
-                "signal_strength_max":5,
-                "type":"wifi",
-                "interface":"wlan0",
-                "mac":"11:22:33:44:55:66",
-                "dhcp":true,
-                "ip":"192.168.1.1",
-                "carrier":true,
-                "supported":true,
-                "present":true,
-                "configured":true,
-                "status":"connected"
-            }
+impl Cli {
+    /// Fills in anything not given on the command line from the config file.
+    fn merge(&mut self, config: Config) {
+        self.username = self.username.take().or(config.username);
+        self.password = self.password.take().or(config.password);
+        self.token = self.token.take().or(config.token);
+        self.url = self.url.take().or(config.url);
+        self.auto_discover |= config.auto_discover.unwrap_or_default();
+        self.envoy_index = self.envoy_index.or(config.envoy_index);
+        self.auth_mode = self.auth_mode.or(config.auth_mode);
+        self.verbose = self.verbose.max(config.verbose.unwrap_or_default());
+        self.log_level = self.log_level.take().or(config.log_level);
+        self.envoy_serial = self.envoy_serial.take().or(config.envoy_serial);
+        self.insecure |= config.insecure.unwrap_or_default();
+        self.output_format = self.output_format.or(config.output_format);
+        self.interval = self.interval.or(config.interval);
+        self.max_errors = self.max_errors.or(config.max_errors);
+        self.timeout = self.timeout.or(config.timeout);
+        self.connect_timeout_ms = self.connect_timeout_ms.or(config.connect_timeout_ms);
+        self.read_timeout_ms = self.read_timeout_ms.or(config.read_timeout_ms);
+        self.max_retries = self.max_retries.or(config.max_retries);
+        self.retry_base_delay_ms = self.retry_base_delay_ms.or(config.retry_base_delay_ms);
+        self.csv_delimiter = self.csv_delimiter.or(config.csv_delimiter);
+        self.csv_no_header |= config.csv_no_header.unwrap_or_default();
+        self.livedata |= config.livedata.unwrap_or_default();
+        self.events = self.events.or(config.events);
+        self.events_state = self.events_state.take().or(config.events_state);
+        self.stale_threshold_minutes = self
+            .stale_threshold_minutes
+            .or(config.stale_threshold_minutes);
+        self.freshness = self.freshness.or(config.freshness);
+        self.use_report_time |= config.use_report_time.unwrap_or_default();
+        self.detailed_inverters |= config.detailed_inverters.unwrap_or_default();
+        self.enable_ev_charger |= config.enable_ev_charger.unwrap_or_default();
+        self.enable_meters |= config.enable_meters.unwrap_or_default();
+        self.enable_firmware |= config.enable_firmware.unwrap_or_default();
+        self.enable_battery |= config.enable_battery.unwrap_or_default();
+        self.network_diagnostics |= config.network_diagnostics.unwrap_or_default();
+        self.network_diagnostics_timeout_ms = self
+            .network_diagnostics_timeout_ms
+            .or(config.network_diagnostics_timeout_ms);
+        if self.filter_serial.is_empty() {
+            self.filter_serial = config.filter_serial.unwrap_or_default();
+        }
+        self.stream |= config.stream.unwrap_or_default();
+        self.csv_single_table |= config.csv_single_table.unwrap_or_default();
+        self.influx_url = self.influx_url.take().or(config.influx_url);
+        self.influx_org = self.influx_org.take().or(config.influx_org);
+        self.influx_bucket = self.influx_bucket.take().or(config.influx_bucket);
+        self.influx_token = self.influx_token.take().or(config.influx_token);
+        self.influx_batch_size = self.influx_batch_size.or(config.influx_batch_size);
+        self.output_file = self.output_file.take().or(config.output_file);
+        self.append |= config.append.unwrap_or_default();
+        self.also_stdout |= config.also_stdout.unwrap_or_default();
+        self.measurement_prefix = self.measurement_prefix.take().or(config.measurement_prefix);
+        if self.tags.is_empty() {
+            self.tags = config.tag.unwrap_or_default().into_iter().collect();
+        }
+        self.mqtt_broker = self.mqtt_broker.take().or(config.mqtt_broker);
+        self.mqtt_topic_prefix = self.mqtt_topic_prefix.take().or(config.mqtt_topic_prefix);
+        self.mqtt_qos = self.mqtt_qos.or(config.mqtt_qos);
+        self.mqtt_username = self.mqtt_username.take().or(config.mqtt_username);
+        self.mqtt_password = self.mqtt_password.take().or(config.mqtt_password);
+        self.mqtt_retained |= config.mqtt_retained.unwrap_or_default();
+        self.statsd_host = self.statsd_host.take().or(config.statsd_host);
+        self.statsd_prefix = self.statsd_prefix.take().or(config.statsd_prefix);
+        self.statsd_format = self.statsd_format.or(config.statsd_format);
+    }
+
+    /// A serial number is only useful for fetching a token, so it implies bearer-token auth.
+    fn auth_mode(&self) -> AuthMode {
+        self.auth_mode.unwrap_or(match self.envoy_serial {
+            Some(_) => AuthMode::BearerToken,
+            None => AuthMode::Digest,
+        })
+    }
+
+    /// Enforces the requirements clap can't check on its own, since options may also come from
+    /// the config file. Exits the same way clap does for a missing argument.
+    /// The first pair of options that can't be used together. clap checks these on the command
+    /// line, but `--config` is merged in afterwards.
+    fn conflicting_options(&self) -> Option<(&'static str, &'static str)> {
+        [
+            (
+                "--stream",
+                self.stream,
+                "--interval",
+                self.interval.is_some(),
+            ),
+            ("--stream", self.stream, "--livedata", self.livedata),
+            ("--stream", self.stream, "--events", self.events.is_some()),
+            (
+                "--events",
+                self.events.is_some(),
+                "--livedata",
+                self.livedata,
+            ),
+            (
+                "--token",
+                self.token.is_some(),
+                "--auth-mode",
+                self.auth_mode.is_some(),
+            ),
         ]
-    },
-    "comm":{"num":1,"level":1},
-    "alerts":[],
-    "update_status":"satisfied"}
-     */
-    #[serde(with = "chrono::serde::ts_seconds")]
-    software_build_epoch: DateTime<Utc>,
+        .into_iter()
+        .find(|&(_, a, _, b)| a && b)
+        .map(|(a, _, b, _)| (a, b))
+    }
+
+    fn validate(&self) {
+        if let Some((a, b)) = self.conflicting_options() {
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    format!("the argument '{}' cannot be used with '{}'", a, b),
+                )
+                .exit();
+        }
+        let mut missing = Vec::new();
+        if self.url.is_none() && !self.auto_discover {
+            missing.push("--url <URL>");
+        }
+        if self.token.is_none() {
+            if self.username.is_none() {
+                missing.push("--username <USERNAME>");
+            }
+            if self.password.is_none() {
+                missing.push("--password <PASSWORD>");
+            }
+            if matches!(self.auth_mode(), AuthMode::BearerToken) && self.envoy_serial.is_none() {
+                missing.push("--envoy-serial <ENVOY_SERIAL>");
+            }
+        }
+        if self.influx_url.is_some() {
+            if self.influx_org.is_none() {
+                missing.push("--influx-org <INFLUX_ORG>");
+            }
+            if self.influx_bucket.is_none() {
+                missing.push("--influx-bucket <INFLUX_BUCKET>");
+            }
+            if self.influx_token.is_none() {
+                missing.push("--influx-token <INFLUX_TOKEN>");
+            }
+        }
+        if self.mqtt_username.is_some() && self.mqtt_password.is_none() {
+            missing.push("--mqtt-password <MQTT_PASSWORD>");
+        }
+        if let Some(delimiter) = self.csv_delimiter {
+            if !delimiter.is_ascii() {
+                Cli::command()
+                    .error(
+                        ErrorKind::InvalidValue,
+                        "--csv-delimiter must be a single ASCII character",
+                    )
+                    .exit();
+            }
+        }
+        if !missing.is_empty() {
+            Cli::command()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    format!(
+                        "the following required arguments were not provided:\n  {}",
+                        missing.join("\n  ")
+                    ),
+                )
+                .exit();
+        }
+    }
+}
 
-    current_date: String,
+#[derive(Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
 
-    current_time: String,
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> LevelFilter {
+        match level {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
 
-    timezone: String,
+#[derive(Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum AuthMode {
+    /// HTTP digest auth against the Envoy, used by firmware before D7
+    Digest,
+    /// A JWT fetched from Enlighten with the same username and password, used by D7 and later
+    BearerToken,
+}
 
-    #[serde(deserialize_with = "decode_memory_string")]
-    db_size: i32,
+#[derive(Clone, Copy, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum OutputFormat {
+    /// InfluxDB line protocol, for telegraf's exec input
+    #[default]
+    #[value(alias = "influx")]
+    #[serde(alias = "influx")]
+    InfluxLineProtocol,
+    /// Prometheus text exposition format, for the node_exporter textfile collector or a pushgateway
+    Prometheus,
+    /// One JSON object per line, for Filebeat, Vector, or Fluent Bit
+    Jsonl,
+    /// Comma-separated values, for spreadsheets; see the `--csv-*` options
+    Csv,
+}
 
-    #[serde(deserialize_with = "string_to_i32")]
-    db_percent_full: i32,
+/// How scraped points are printed.
+enum Output {
+    LineProtocol,
+    Prometheus,
+    Jsonl,
+    Csv(Box<CsvWriter>),
+}
 
-    network: HomeNetworkResponse,
-    comm: HomeCommResponse,
+impl Backend for Output {
+    fn write(&mut self, points: &[Point]) -> Result<(), EnphaseError> {
+        match self {
+            Output::LineProtocol => {
+                for point in points {
+                    println!("{}", point);
+                }
+            }
+            Output::Prometheus => print!("{}", to_prometheus(points)),
+            Output::Jsonl => print!("{}", to_json_lines(points)),
+            Output::Csv(writer) => writer.write(points)?,
+        }
+        Ok(())
+    }
+}
 
-    #[serde(deserialize_with = "decode_array_to_size")]
-    alerts: usize,
+/// Everywhere scraped points go.
+struct Outputs(Vec<Box<dyn Backend>>);
 
-    update_status: String,
+impl Outputs {
+    /// Writes to every output, even after one fails, so InfluxDB being down doesn't cost MQTT
+    /// this scrape too. When several fail, each is logged and the error returned counts them.
+    #[instrument(skip_all, fields(points = points.len()))]
+    fn write(&mut self, points: &[Point]) -> Result<(), EnphaseError> {
+        let mut errors: Vec<EnphaseError> = self
+            .0
+            .iter_mut()
+            .filter_map(|backend| backend.write(points).err())
+            .collect();
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            failed => {
+                for e in &errors {
+                    error!("writing output: {}", e);
+                }
+                Err(
+                    io::Error::other(format!("{} of {} outputs failed", failed, self.0.len()))
+                        .into(),
+                )
+            }
+        }
+    }
+
+    fn close(self) {
+        for backend in self.0 {
+            backend.close();
+        }
+    }
 }
 
-#[derive(Deserialize, Debug)]
-struct HomeNetworkResponse {
-    #[serde(with = "chrono::serde::ts_seconds")]
-    last_enlighten_report_time: DateTime<Utc>,
+/// An agent for talking to the Envoy, as configured by `cli`.
+fn agent_builder(cli: &Cli) -> AgentBuilder {
+    let timeout = cli.timeout.unwrap_or(30) * 1000;
+    let builder = builder()
+        .timeout_connect(Duration::from_millis(
+            cli.connect_timeout_ms.unwrap_or(timeout),
+        ))
+        .timeout_read(Duration::from_millis(
+            cli.read_timeout_ms.unwrap_or(timeout),
+        ));
+    if cli.insecure {
+        builder.tls_config(insecure_tls_config())
+    } else {
+        builder
+    }
 }
 
-#[derive(Deserialize, Debug)]
-struct HomeCommResponse {
-    num: i32,
-    level: i32,
+/// An agent for logging in to Enlighten, with the same timeouts as the Envoy's.
+fn enlighten_agent(cli: &Cli) -> Agent {
+    let timeout = Duration::from_secs(cli.timeout.unwrap_or(30));
+    builder()
+        .timeout_connect(timeout)
+        .timeout_read(timeout)
+        .build()
 }
 
-fn decode_array_to_size<'de, D>(deserializer: D) -> Result<usize, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    return Ok(serde_json::Value::deserialize(deserializer)?
-        .as_array()
-        .unwrap()
-        .len());
+/// Authenticates and builds the client, set up to re-authenticate when the Envoy asks.
+#[instrument(skip_all, fields(url = cli.url.as_deref()))]
+fn connect(cli: &Cli) -> Result<EnvoyClient, EnphaseError> {
+    // `Cli::validate` has made sure everything needed for the chosen auth is present
+    let url = cli.url.as_deref().unwrap_or_default();
+    let username = cli.username.as_deref().unwrap_or_default();
+    let password = cli.password.as_deref().unwrap_or_default();
+    let max_retries = cli.max_retries.unwrap_or(3);
+    let retry_base_delay = Duration::from_millis(cli.retry_base_delay_ms.unwrap_or(1000));
+    // fetched once up front; every scrape in the daemon loop reuses it until the Envoy rejects it
+    let auth = match (&cli.token, cli.auth_mode()) {
+        (Some(token), _) => format!("Bearer {}", token),
+        (None, AuthMode::Digest) => {
+            let agent = agent_builder(cli).build();
+            retry(
+                || get_auth_header(&agent, url, username, password),
+                max_retries.saturating_add(1),
+                retry_base_delay,
+            )?
+        }
+        (None, AuthMode::BearerToken) => {
+            let serial = cli.envoy_serial.as_deref().unwrap_or_default();
+            let agent = enlighten_agent(cli);
+            let token = retry(
+                || fetch_enlighten_token(&agent, username, password, serial),
+                max_retries.saturating_add(1),
+                retry_base_delay,
+            )?;
+            format!("Bearer {}", token)
+        }
+    };
+    let client = EnvoyClient::with_authorization(agent_builder(cli), url, auth)
+        .with_retries(max_retries, retry_base_delay);
+    // digest nonces and tokens expire, which a daemon outlives; a `--token` can't be renewed
+    Ok(match (&cli.token, cli.auth_mode()) {
+        (Some(_), _) => client,
+        (None, AuthMode::Digest) => {
+            let agent = agent_builder(cli).build();
+            let (url, username, password) =
+                (url.to_string(), username.to_string(), password.to_string());
+            client
+                .with_reauthentication(move || get_auth_header(&agent, &url, &username, &password))
+        }
+        (None, AuthMode::BearerToken) => {
+            let (username, password) = (username.to_string(), password.to_string());
+            let serial = cli.envoy_serial.clone().unwrap_or_default();
+            let agent = enlighten_agent(cli);
+            client.with_reauthentication(move || {
+                fetch_enlighten_token(&agent, &username, &password, &serial)
+                    .map(|token| format!("Bearer {}", token))
+            })
+        }
+    })
 }
 
-fn string_to_i32<'de, D>(deserializer: D) -> Result<i32, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    return Ok(String::deserialize(deserializer)?.parse::<i32>().unwrap());
+impl Outputs {
+    /// Sets up every output asked for on the command line.
+    fn new(cli: &Cli) -> Result<Outputs, EnphaseError> {
+        let mut backends: Vec<Box<dyn Backend>> = Vec::new();
+        let influx = cli.influx_url.as_ref().map(|influx_url| {
+            InfluxV2Backend::new(
+                influx_url,
+                cli.influx_token.as_deref().unwrap_or_default(),
+                cli.influx_org.as_deref().unwrap_or_default(),
+                cli.influx_bucket.as_deref().unwrap_or_default(),
+                cli.influx_batch_size.unwrap_or(100),
+                Duration::from_secs(cli.timeout.unwrap_or(30)),
+            )
+        });
+        let file = match &cli.output_file {
+            Some(path) if path.as_os_str() != "-" => Some(FileBackend::open(path, cli.append)?),
+            _ => None,
+        };
+        // writing to InfluxDB or a file replaces printing unless both are asked for
+        if (influx.is_none() && file.is_none()) || cli.also_stdout {
+            backends.push(Box::new(match cli.output_format.unwrap_or_default() {
+                OutputFormat::InfluxLineProtocol => Output::LineProtocol,
+                OutputFormat::Prometheus => Output::Prometheus,
+                OutputFormat::Jsonl => Output::Jsonl,
+                // `Cli::validate` has checked the delimiter is ASCII
+                OutputFormat::Csv => Output::Csv(Box::new(CsvWriter::new(
+                    cli.csv_delimiter.unwrap_or(',') as u8,
+                    !cli.csv_no_header,
+                    cli.csv_single_table,
+                ))),
+            }));
+        }
+        if let Some(file) = file {
+            backends.push(Box::new(file));
+        }
+        if let Some(influx) = influx {
+            backends.push(Box::new(influx));
+        }
+        if let Some(broker) = &cli.mqtt_broker {
+            backends.push(Box::new(MqttBackend::connect(
+                broker,
+                cli.mqtt_username
+                    .as_deref()
+                    .zip(cli.mqtt_password.as_deref()),
+                cli.mqtt_topic_prefix.as_deref().unwrap_or("enphase"),
+                cli.mqtt_qos.unwrap_or_default(),
+                cli.mqtt_retained,
+            )?));
+        }
+        if cli.statsd_host.is_some() || cli.statsd_prefix.is_some() || cli.statsd_format.is_some() {
+            backends.push(Box::new(StatsdBackend::connect(
+                cli.statsd_host.as_deref().unwrap_or("127.0.0.1:8125"),
+                cli.statsd_prefix.as_deref().unwrap_or("enphase"),
+                cli.statsd_format.unwrap_or_default(),
+            )?));
+        }
+        Ok(Outputs(backends))
+    }
 }
 
-fn decode_memory_string<'de, D>(deserializer: D) -> Result<i32, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s = String::deserialize(deserializer)?;
-    let mut iter = s.split_ascii_whitespace();
-    let mut number = iter.next().unwrap().parse::<i32>().unwrap();
-    let multiple = iter.next().unwrap().to_ascii_uppercase();
-    if multiple == "MB" {
-        number *= 1024 * 1024;
-    } else if multiple == "GB" {
-        number *= 1024 * 1024 * 1024;
-    } else if multiple == "KB" {
-        number *= 1024;
-    }
-    return Ok(number);
+fn run(cli: Cli) -> Result<(), EnphaseError> {
+    if cli.dry_run {
+        let client = connect(&cli)?;
+        eprintln!("auth OK");
+        return dry_run(&client, &cli);
+    }
+    let mut outputs = Outputs::new(&cli)?;
+    let result = match cli.interval {
+        Some(seconds) if !cli.stream => {
+            // the Envoy may be rebooting when the daemon starts, so keep trying on each tick
+            let mut connected = None;
+            let scrape = || {
+                let (client, info) = match &connected {
+                    Some(connected) => connected,
+                    None => connected.insert(connect_or_report_down(&cli, &mut outputs)?),
+                };
+                scrape_and_print(client, info.as_ref(), &cli, &mut outputs)
+            };
+            daemon(
+                Duration::from_secs(seconds),
+                cli.max_errors,
+                cli.insecure,
+                scrape,
+            )
+        }
+        _ => connect_or_report_down(&cli, &mut outputs).and_then(|(client, info)| {
+            if cli.stream {
+                stream(&client, info.as_ref(), &cli, &mut outputs)
+            } else {
+                scrape_and_print(&client, info.as_ref(), &cli, &mut outputs)
+            }
+        }),
+    };
+    outputs.close();
+    result
 }
 
-fn get_home(agent: &Agent, url: &String) -> HomeResponse {
-    let body = agent
-        .get(&format!("{}/{}", url, "home.json"))
-        .call()
-        .unwrap();
-    return body.into_json().unwrap();
+/// Connects and fetches `info.xml`, which is only done once since the serial doesn't change. If
+/// the Envoy can't be reached there's nothing else to report, but that it's down is worth
+/// reporting, so this writes `envoy_up` as 0.
+fn connect_or_report_down(
+    cli: &Cli,
+    outputs: &mut Outputs,
+) -> Result<(EnvoyClient, Option<InfoResponse>), EnphaseError> {
+    let connected = connect(cli).and_then(|client| {
+        let info = client.info()?;
+        if let Some(info) = &info {
+            info!(
+                "Envoy {} is running {}",
+                info.device.serial_number, info.device.software
+            );
+        }
+        Ok((client, info))
+    });
+    if connected.is_err() {
+        let mut points = Vec::new();
+        envoy_up_to_influx(false, now_nano(), &mut points);
+        label(None, cli, &mut points);
+        if let Err(write_error) = outputs.write(&points) {
+            print_error(&write_error, cli.insecure);
+        }
+    }
+    connected
+}
+
+/// Calls `scrape` every `interval` until SIGINT or SIGTERM, or until it has failed `max_errors`
+/// times in a row.
+fn daemon(
+    interval: Duration,
+    max_errors: Option<u32>,
+    insecure: bool,
+    mut scrape: impl FnMut() -> Result<(), EnphaseError>,
+) -> Result<(), EnphaseError> {
+    let stopped = stop_signal()?;
+    let mut failures = 0;
+    loop {
+        let started = Instant::now();
+        match scrape() {
+            Ok(()) => failures = 0,
+            Err(e) => {
+                failures += 1;
+                if max_errors.is_some_and(|max| failures >= max) {
+                    return Err(e);
+                }
+                // one bad scrape shouldn't take the daemon down; try again next tick
+                print_error(&e, insecure);
+            }
+        }
+        if stopped
+            .recv_timeout(interval.saturating_sub(started.elapsed()))
+            .is_ok()
+        {
+            info!("stopping");
+            return Ok(());
+        }
+    }
+}
+
+/// Follows `stream/meter` until SIGINT or SIGTERM, writing each event as it arrives. The Envoy
+/// hangs up every few minutes, so this reconnects, backing off from `--retry-base-delay-ms` for
+/// as long as connecting keeps failing.
+fn stream(
+    client: &EnvoyClient,
+    info: Option<&InfoResponse>,
+    cli: &Cli,
+    outputs: &mut Outputs,
+) -> Result<(), EnphaseError> {
+    const MAX_DELAY: Duration = Duration::from_secs(60);
+    let base_delay = Duration::from_millis(cli.retry_base_delay_ms.unwrap_or(1000));
+    let insecure = cli.insecure;
+    let stopped = stop_signal()?;
+    let mut delay = base_delay;
+    loop {
+        match client.stream_meter() {
+            Ok(events) => {
+                for event in events {
+                    if stopped.try_recv().is_ok() {
+                        info!("stopping");
+                        return Ok(());
+                    }
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(e) => {
+                            print_error(&e, insecure);
+                            break;
+                        }
+                    };
+                    delay = base_delay;
+                    let mut points = Vec::new();
+                    // the Envoy doesn't timestamp the events, so they're stamped as they arrive
+                    meter_stream_to_influx(event, now_nano(), &mut points);
+                    label(info, cli, &mut points);
+                    // like a failed scrape in the daemon, a failed write only loses this event
+                    if let Err(e) = outputs.write(&points) {
+                        print_error(&e, insecure);
+                    }
+                }
+                warn!("stream/meter ended; reconnecting in {:?}", delay);
+            }
+            Err(e) if e.is_retriable() => print_error(&e, insecure),
+            Err(e) => return Err(e),
+        }
+        if stopped.recv_timeout(delay).is_ok() {
+            info!("stopping");
+            return Ok(());
+        }
+        delay = delay.saturating_mul(2).min(MAX_DELAY);
+    }
 }
 
-fn home_to_influx(home: HomeResponse) {
+/// Receives a message when SIGINT or SIGTERM arrives.
+fn stop_signal() -> Result<mpsc::Receiver<()>, EnphaseError> {
+    let (stop, stopped) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = stop.send(());
+    })
+    .map_err(io::Error::other)?;
+    Ok(stopped)
+}
+
+/// Scrapes and prints whatever was collected, even if some endpoint failed partway through. When
+/// the Envoy's `info.xml` is known, every point is tagged with its serial number so data from
+/// several Envoys stays separate.
+fn scrape_and_print(
+    client: &EnvoyClient,
+    info: Option<&InfoResponse>,
+    cli: &Cli,
+    outputs: &mut Outputs,
+) -> Result<(), EnphaseError> {
+    // one timestamp for the whole scrape, so its points line up
+    let timestamp_nano = now_nano();
+    let mut points = Vec::new();
+    if let Some(info) = info {
+        info_to_influx(info, timestamp_nano, &mut points);
+        if cli.enable_firmware {
+            firmware_to_influx(info, timestamp_nano, &mut points);
+        }
+    }
+    let mut last_event = None;
+    let result = if cli.livedata {
+        scrape_livedata(client, timestamp_nano, &mut points)
+    } else if let Some(length) = cli.events {
+        scrape_events(client, cli, length, &mut points).map(|id| last_event = id)
+    } else {
+        scrape(client, cli, timestamp_nano, &mut points)
+    };
+    // first, so it's the one line sure to be there
+    let mut up = Vec::new();
+    envoy_up_to_influx(result.is_ok(), timestamp_nano, &mut up);
+    points.splice(0..0, up);
+    label(info, cli, &mut points);
+    outputs.write(&points)?;
+    // only once they're written, so a failed write gets them sent again next time
+    if let Some(id) = last_event {
+        fs::write(events_state(cli), format!("{}\n", id))?;
+    }
+    result
+}
+
+fn now_nano() -> u128 {
     let now = SystemTime::now();
-    let timestamp_nano = now.duration_since(UNIX_EPOCH).unwrap().as_nanos();
-    println!(
-        "software_build_date value={} {}",
-        home.software_build_epoch.timestamp_nanos(),
-        timestamp_nano
-    );
-    println!(
-        "database total_size={},percent_full={} {}",
-        home.db_size, home.db_percent_full, timestamp_nano
+    now.duration_since(UNIX_EPOCH).unwrap().as_nanos()
+}
+
+/// Tags every point with the Envoy's serial number, when it's known, and with `--tag`, and puts
+/// `--measurement-prefix` in front of its measurement.
+fn label(info: Option<&InfoResponse>, cli: &Cli, points: &mut [Point]) {
+    for point in points {
+        if let Some(prefix) = &cli.measurement_prefix {
+            point.measurement.insert_str(0, prefix);
+        }
+        if let Some(info) = info {
+            point.tags.push((
+                "envoy_serial".to_string(),
+                info.device.serial_number.clone(),
+            ));
+        }
+        point.tags.extend(cli.tags.iter().cloned());
+    }
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), String> {
+    match tag.split_once('=') {
+        Some((key, value)) if !key.is_empty() && !value.is_empty() => {
+            Ok((key.to_string(), value.to_string()))
+        }
+        _ => Err(format!("{:?} isn't KEY=VALUE", tag)),
+    }
+}
+
+/// Fetches every endpoint once, adding the results to `points`.
+#[instrument(skip_all)]
+fn scrape(
+    client: &EnvoyClient,
+    cli: &Cli,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) -> Result<(), EnphaseError> {
+    let home = client.home()?;
+    let clock = client.date_time_display()?;
+    if let Some(clock) = &clock {
+        clock_config_to_influx(clock, timestamp_nano, points);
+    }
+    home_to_influx(home, clock.as_ref(), timestamp_nano, points);
+
+    let inverters = client.inverters()?;
+    let inverters = filter_inverters(inverters, &cli.filter_serial, timestamp_nano, points);
+    let stale_threshold = chrono::Duration::minutes(cli.stale_threshold_minutes.unwrap_or(20));
+    let stale_after = chrono::Duration::seconds(cli.freshness.unwrap_or(900));
+    inverter_status_to_influx(
+        &inverters,
+        stale_threshold,
+        stale_after,
+        timestamp_nano,
+        points,
     );
-    println!(
-        "phone_home update_status=\"{}\",alerts={},last_report={} {}",
-        home.update_status,
-        home.alerts,
-        home.network.last_enlighten_report_time.timestamp_nanos(),
-        timestamp_nano
+    let mut energy = client.inverter_energy()?;
+    if !cli.filter_serial.is_empty() {
+        energy.retain(|serial, _| cli.filter_serial.contains(serial));
+    }
+    inverters_to_influx(
+        &inverters,
+        &energy,
+        cli.use_report_time,
+        stale_after,
+        timestamp_nano,
+        points,
     );
-    let zone = TZ_VARIANTS
-        .into_iter()
-        .filter(|t: &Tz| {
-            let date = t.timestamp_nanos(timestamp_nano.try_into().unwrap());
-            return date.offset().tz_id() == home.timezone;
-        })
-        .next()
-        .unwrap();
-    let device_datetime = zone
-        .datetime_from_str(
-            &format!("{} {}", home.current_date, home.current_time),
-            "%m/%d/%Y %H:%M",
-        )
-        .unwrap();
-    println!(
-        "device_time_skew device_timestamp={} {}",
-        device_datetime.timestamp_nanos(),
-        timestamp_nano
+    fleet_to_influx(&inverters, timestamp_nano, points);
+    inverter_summary_to_influx(&inverters, stale_after, timestamp_nano, points);
+
+    if cli.detailed_inverters {
+        let devices = client.device_data()?;
+        device_data_to_influx(devices, timestamp_nano, points);
+    }
+
+    let production = client.production()?;
+    production_to_influx(production, timestamp_nano, points);
+
+    let summary = client.production_summary()?;
+    production_summary_to_influx(summary, timestamp_nano, points);
+
+    if let Some(summary) = client.consumption_summary()? {
+        consumption_summary_to_influx(summary, timestamp_nano, points);
+    }
+
+    let meters = client.meter_config()?;
+    meter_config_to_influx(meters, timestamp_nano, points);
+
+    let cts = client.ct_config()?;
+    ct_config_to_influx(cts, timestamp_nano, points);
+
+    let readings = client.meter_readings()?;
+    meter_readings_to_influx(readings, timestamp_nano, points);
+
+    if cli.enable_meters {
+        if let Some(meters) = client.meters()? {
+            meters_to_influx(meters, timestamp_nano, points);
+        }
+    }
+
+    let reports = client.consumption_reports()?;
+    consumption_reports_to_influx(reports, timestamp_nano, points);
+
+    let inventory = client.inventory()?;
+    inventory_to_influx(inventory, timestamp_nano, points);
+
+    let ensemble = client.ensemble_inventory()?;
+    let batteries = client.ensemble_power()?;
+    battery_to_influx(
+        &ensemble,
+        &batteries,
+        cli.enable_battery,
+        timestamp_nano,
+        points,
     );
-    println!("comm number={},level={} {}", home.comm.num, home.comm.level, timestamp_nano);
+    ensemble_inventory_to_influx(ensemble, timestamp_nano, points);
+    battery_power_to_influx(batteries, timestamp_nano, points);
+
+    if let Some(status) = client.ensemble_status()? {
+        ensemble_status_to_influx(status, timestamp_nano, points);
+    }
+
+    if let Some(relay) = client.grid_relay()? {
+        grid_relay_to_influx(relay, timestamp_nano, points);
+    }
+
+    let contacts = client.dry_contacts()?;
+    dry_contacts_to_influx(contacts, timestamp_nano, points);
+
+    if let Some(generator) = client.generator()? {
+        generator_to_influx(generator, timestamp_nano, points);
+    }
+
+    if let Some(config) = client.generator_config()? {
+        generator_config_to_influx(config, timestamp_nano, points);
+    }
+
+    if let Some(grid_profile) = client.grid_profile()? {
+        grid_profile_to_influx(grid_profile, timestamp_nano, points);
+    }
+
+    if let Some(wifi) = client.wireless_display()? {
+        wifi_to_influx(wifi, timestamp_nano, points);
+    }
+
+    if let Some(scan) = client.new_scan()? {
+        device_scan_to_influx(scan, timestamp_nano, points);
+    }
+
+    if let Some(limit) = client.pv_limit()? {
+        pv_limit_to_influx(limit, timestamp_nano, points);
+    }
+
+    if let Some(tariff) = client.tariff()? {
+        tariff_to_influx(tariff, timestamp_nano, points);
+    }
+
+    if cli.enable_ev_charger {
+        if let Some(charger) = client.ev_charger()? {
+            ev_charger_to_influx(charger, timestamp_nano, points);
+        }
+    }
+
+    if cli.network_diagnostics {
+        let timeout = Duration::from_millis(cli.network_diagnostics_timeout_ms.unwrap_or(30000));
+        if let Some(diagnostics) = client.network_display(timeout)? {
+            network_display_to_influx(diagnostics, timestamp_nano, points);
+        }
+    }
+    Ok(())
 }
 
-fn get_inverters(agent: &Agent, url: &String) -> Vec<InvertersResponse> {
-    let body = agent
-        .get(&format!("{}/{}", url, "api/v1/production/inverters"))
-        .call()
-        .unwrap();
-    return body.into_json().unwrap();
+/// Fetches the live power flows, first turning on the Envoy's live stream if it's off. Until the
+/// stream is on the values are stale, so nothing is added that time.
+fn scrape_livedata(
+    client: &EnvoyClient,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) -> Result<(), EnphaseError> {
+    let livedata = client.livedata()?;
+    if livedata.connection.sc_stream != "enabled" {
+        return client.enable_livedata_stream();
+    }
+    livedata_to_influx(livedata, timestamp_nano, points);
+    Ok(())
 }
 
-fn inverters_to_influx(inverters: Vec<InvertersResponse>) {
-    let now = SystemTime::now();
-    let timestamp_nano = now.duration_since(UNIX_EPOCH).unwrap().as_nanos();
-    for inverter in &inverters {
-        println!(
-            "inverter,serial_number=\"{}\" last_report={},last_watts={},max_watts={} {}",
-            inverter.serial_number,
-            inverter.last_report_date.timestamp_nanos(),
-            inverter.last_report_watts,
-            inverter.max_report_watts,
-            timestamp_nano
+/// Adds the events logged since the last one in `--events-state`, returning the newest one's id.
+fn scrape_events(
+    client: &EnvoyClient,
+    cli: &Cli,
+    length: usize,
+    points: &mut Vec<Point>,
+) -> Result<Option<i64>, EnphaseError> {
+    let path = events_state(cli);
+    let last_event =
+        match fs::read_to_string(&path) {
+            Ok(contents) => Some(contents.trim().parse::<i64>().map_err(|_| {
+                EnphaseError::Parse(format!("last event id in {}", path.display()))
+            })?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+    let home = client.home()?;
+    let clock = client.date_time_display()?;
+    // the log's times are local; leave the events for a scrape that can read them, rather than
+    // reporting the Envoy as down
+    let zone = match envoy_timezone(&home, clock.as_ref()) {
+        Ok(zone) => zone,
+        Err(e) => {
+            warn!("leaving out the event log: {}", e);
+            return Ok(None);
+        }
+    };
+    let mut events = client.events(length)?;
+    events.retain(|event| last_event.is_none_or(|last| event.id > last));
+    events.sort_by_key(|event| event.id);
+    events_to_influx(&events, zone, points);
+    Ok(events.last().map(|event| event.id))
+}
+
+fn events_state(cli: &Cli) -> PathBuf {
+    cli.events_state
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("enphase-events.state"))
+}
+
+/// Fetches every endpoint a scrape with these options would, reporting how each went on stderr.
+/// Keeps going past failures so they're all reported, then returns the first. Nothing is changed
+/// on the Envoy or on disk: the live stream isn't turned on and `--events-state` isn't written.
+fn dry_run(client: &EnvoyClient, cli: &Cli) -> Result<(), EnphaseError> {
+    let mut first_error = None;
+    let mut check = |endpoint: &str, result: Result<String, EnphaseError>| match result {
+        Ok(summary) if summary.is_empty() => eprintln!("{} OK", endpoint),
+        Ok(summary) => eprintln!("{} OK: {}", endpoint, summary),
+        Err(e) => {
+            eprintln!("{} FAILED: {}", endpoint, e);
+            first_error.get_or_insert(e);
+        }
+    };
+    let devices = |n: usize| format!("{} devices", n);
+    let optional = |found: bool| if found { "" } else { "not present" }.to_string();
+    check(
+        "info.xml",
+        client.info().map(|info| match info {
+            Some(info) => format!("serial {}", info.device.serial_number),
+            None => optional(false),
+        }),
+    );
+    if cli.livedata {
+        check(
+            "ivp/livedata/status",
+            client
+                .livedata()
+                .map(|l| format!("stream {}", l.connection.sc_stream)),
         );
+        return first_error.map_or(Ok(()), Err);
     }
+    check("home.json", client.home().map(|_| String::new()));
+    check(
+        "admin/lib/date_time_display.json",
+        client
+            .date_time_display()
+            .map(|c| c.map_or_else(|| optional(false), |c| c.tz)),
+    );
+    if let Some(length) = cli.events {
+        check(
+            "datatab/event_dt.rb",
+            client.events(length).map(|e| format!("{} events", e.len())),
+        );
+        return first_error.map_or(Ok(()), Err);
+    }
+    check("inverters", client.inverters().map(|i| devices(i.len())));
+    check(
+        "ivp/pdm/energy",
+        client.inverter_energy().map(|e| devices(e.len())),
+    );
+    if cli.detailed_inverters {
+        check(
+            "ivp/pdm/device_data",
+            client.device_data().map(|d| devices(d.len())),
+        );
+    }
+    check(
+        "production.json",
+        client.production().map(|_| String::new()),
+    );
+    check(
+        "production",
+        client.production_summary().map(|_| String::new()),
+    );
+    check(
+        "consumption",
+        client.consumption_summary().map(|c| optional(c.is_some())),
+    );
+    check("meters", client.meter_config().map(|m| devices(m.len())));
+    check(
+        "meter readings",
+        client.meter_readings().map(|m| devices(m.len())),
+    );
+    check(
+        "consumption reports",
+        client
+            .consumption_reports()
+            .map(|r| format!("{} reports", r.len())),
+    );
+    check(
+        "inventory.json",
+        client
+            .inventory()
+            .map(|i| devices(i.iter().map(|group| group.devices.len()).sum())),
+    );
+    check(
+        "ensemble inventory",
+        client
+            .ensemble_inventory()
+            .map(|i| format!("{} groups", i.len())),
+    );
+    check(
+        "ensemble power",
+        client.ensemble_power().map(|b| devices(b.len())),
+    );
+    check(
+        "ensemble status",
+        client.ensemble_status().map(|s| optional(s.is_some())),
+    );
+    check(
+        "grid relay",
+        client.grid_relay().map(|r| optional(r.is_some())),
+    );
+    check(
+        "dry contacts",
+        client
+            .dry_contacts()
+            .map(|c| format!("{} contacts", c.len())),
+    );
+    check(
+        "generator",
+        client.generator().map(|g| optional(g.is_some())),
+    );
+    check(
+        "installer/agf/index.json",
+        client
+            .grid_profile()
+            .map(|p| p.map_or_else(|| optional(false), |p| p.selected_profile)),
+    );
+    check(
+        "admin/lib/wireless_display.json",
+        client.wireless_display().map(|w| optional(w.is_some())),
+    );
+    check(
+        "ivp/peb/newscan",
+        client.new_scan().map(|s| optional(s.is_some())),
+    );
+    check(
+        "ivp/meters/cts",
+        client.ct_config().map(|c| format!("{} CTs", c.len())),
+    );
+    check(
+        "ivp/ss/gen_config",
+        client.generator_config().map(|g| optional(g.is_some())),
+    );
+    check(
+        "ivp/sc/pvlimit",
+        client.pv_limit().map(|l| optional(l.is_some())),
+    );
+    check(
+        "admin/lib/tariff",
+        client.tariff().map(|t| optional(t.is_some())),
+    );
+    if cli.enable_ev_charger {
+        check(
+            "ivp/peb/reglsoc",
+            client.ev_charger().map(|c| optional(c.is_some())),
+        );
+    }
+    if cli.network_diagnostics {
+        let timeout = Duration::from_millis(cli.network_diagnostics_timeout_ms.unwrap_or(30000));
+        check(
+            "admin/lib/network_display.json",
+            client
+                .network_display(timeout)
+                .map(|d| optional(d.is_some())),
+        );
+    }
+    first_error.map_or(Ok(()), Err)
 }
 
-fn get_auth_header(url: &String, username: &String, password: &String) -> String {
-    let auth_response = ureq::get(&format!("{}/installer/setup/home", url))
-        .call()
-        .expect_err("Was expecting a 401 error from the server; no idea what to do now")
-        .into_response()
-        .unwrap();
-    let response_header = auth_response.header("WWW-Authenticate").unwrap();
-    let mut password_client = PasswordClient::try_from(response_header).unwrap();
-    let auth_header = password_client
-        .respond(&http_auth::PasswordParams {
-            username,
-            password,
-            uri: "*",
-            method: "GET",
-            body: Some(&[]),
-        })
-        .unwrap();
-    return auth_header;
+/// Picks the Envoy to use from those on the local network. Exits with a usage error listing them
+/// when there's more than one and `envoy_index` doesn't say which.
+fn discover_url(envoy_index: Option<usize>) -> Result<String, EnphaseError> {
+    let mut envoys = discover_envoys(Duration::from_secs(3))?;
+    let list = || {
+        let lines: Vec<String> = envoys
+            .iter()
+            .enumerate()
+            .map(|(i, envoy)| format!("  {}: {} at {}", i, envoy.name, envoy.url))
+            .collect();
+        lines.join("\n")
+    };
+    let index = match (envoys.len(), envoy_index) {
+        (0, _) => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no Envoy answered mDNS on the local network",
+            )
+            .into())
+        }
+        (_, Some(index)) if index >= envoys.len() => Cli::command()
+            .error(
+                ErrorKind::InvalidValue,
+                format!(
+                    "--envoy-index {} is out of range; found:\n{}",
+                    index,
+                    list()
+                ),
+            )
+            .exit(),
+        (_, Some(index)) => index,
+        (1, None) => 0,
+        (_, None) => Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                format!(
+                    "found several Envoys; pick one with --envoy-index:\n{}",
+                    list()
+                ),
+            )
+            .exit(),
+    };
+    let envoy = envoys.swap_remove(index);
+    info!("using {} at {}", envoy.name, envoy.url);
+    Ok(envoy.url)
+}
+
+fn print_error(e: &EnphaseError, insecure: bool) {
+    eprintln!("error: {}", e);
+    if !insecure && e.is_untrusted_certificate() {
+        eprintln!("hint: Envoys use a self-signed certificate; pass --insecure to accept it");
+    }
 }
 
-fn main() {
-    let cli = Cli::parse();
-    let auth = get_auth_header(&cli.url, &cli.username, &cli.password);
-    let basic_auth = move |req: Request, next: MiddlewareNext| -> Result<Response, ureq::Error> {
-        return next.handle(req.set("Authorization", &auth));
+fn main() -> ExitCode {
+    let mut cli = Cli::parse();
+    if let Some(path) = &cli.config {
+        match Config::load(path) {
+            Ok(config) => cli.merge(config),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    cli.validate();
+    let level = match (cli.log_level, cli.verbose) {
+        (Some(level), _) => level.into(),
+        (None, 0) => LevelFilter::WARN,
+        (None, 1) => LevelFilter::INFO,
+        (None, _) => LevelFilter::DEBUG,
     };
-    let agent = builder().middleware(basic_auth).build();
+    // stdout is for the points
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(level.into())
+                .from_env_lossy(),
+        )
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .init();
+    let insecure = cli.insecure;
+    if cli.url.is_none() {
+        // `Cli::validate` has made sure `--auto-discover` was given
+        match discover_url(cli.envoy_index) {
+            Ok(url) => cli.url = Some(url),
+            Err(e) => {
+                print_error(&e, insecure);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            print_error(&e, insecure);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    let home = get_home(&agent, &cli.url);
-    home_to_influx(home);
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    /// Counts its writes, failing every one if `fail`.
+    struct Counting {
+        writes: Arc<AtomicUsize>,
+        fail: bool,
+    }
+
+    impl Backend for Counting {
+        fn write(&mut self, _points: &[Point]) -> Result<(), EnphaseError> {
+            self.writes.fetch_add(1, Ordering::Relaxed);
+            match self.fail {
+                true => Err(io::Error::other("down").into()),
+                false => Ok(()),
+            }
+        }
+    }
 
-    let inverters = get_inverters(&agent, &cli.url);
-    inverters_to_influx(inverters);
+    fn outputs(fail: &[bool]) -> (Outputs, Vec<Arc<AtomicUsize>>) {
+        let writes: Vec<_> = fail.iter().map(|_| Arc::new(AtomicUsize::new(0))).collect();
+        let backends = fail
+            .iter()
+            .zip(&writes)
+            .map(|(&fail, writes)| {
+                Box::new(Counting {
+                    writes: writes.clone(),
+                    fail,
+                }) as Box<dyn Backend>
+            })
+            .collect();
+        (Outputs(backends), writes)
+    }
+
+    fn merged(args: &[&str], config: &str) -> Cli {
+        let mut cli = Cli::parse_from([&["enphase-telegraf"], args].concat());
+        cli.merge(toml::from_str(config).unwrap());
+        cli
+    }
+
+    #[test]
+    fn config_file_options_are_checked_for_conflicts() {
+        let cli = merged(&["--stream"], "interval = 60");
+        assert_eq!(cli.conflicting_options(), Some(("--stream", "--interval")));
+        let cli = merged(&[], "events = 10\nlivedata = true");
+        assert_eq!(cli.conflicting_options(), Some(("--events", "--livedata")));
+        let cli = merged(&["--interval", "60"], "livedata = true");
+        assert_eq!(cli.conflicting_options(), None);
+    }
+
+    #[test]
+    fn token_from_config_conflicts_with_auth_mode() {
+        let cli = merged(&["--auth-mode", "digest"], "token = \"abc\"");
+        assert_eq!(cli.conflicting_options(), Some(("--token", "--auth-mode")));
+        let cli = merged(&["--token", "abc"], "auth_mode = \"digest\"");
+        assert_eq!(cli.conflicting_options(), Some(("--token", "--auth-mode")));
+    }
+
+    #[test]
+    fn a_quiet_envoy_times_out_and_is_retried() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        // accept, then never answer
+        let _held = std::thread::spawn(move || listener.accept().map(|(socket, _)| socket));
+        let cli = Cli::parse_from(["enphase-telegraf", "--timeout", "1"]);
+        let error = enphase::home::get_home(&agent_builder(&cli).build(), &url).unwrap_err();
+        assert!(error.is_retriable(), "{}", error);
+    }
+
+    #[test]
+    fn a_failed_output_doesnt_stop_the_rest() {
+        let (mut outputs, writes) = outputs(&[true, false]);
+        let error = outputs.write(&[]).unwrap_err();
+        assert!(error.to_string().contains("down"), "{}", error);
+        assert!(writes.iter().all(|w| w.load(Ordering::Relaxed) == 1));
+    }
+
+    #[test]
+    fn failed_outputs_are_counted() {
+        let (mut outputs, writes) = outputs(&[true, false, true]);
+        let error = outputs.write(&[]).unwrap_err();
+        assert!(
+            error.to_string().contains("2 of 3 outputs failed"),
+            "{}",
+            error
+        );
+        assert!(writes.iter().all(|w| w.load(Ordering::Relaxed) == 1));
+    }
 }
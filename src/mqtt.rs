@@ -0,0 +1,194 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use enphase::EnphaseError;
+use rumqttc::{Client, Event, MqttOptions, Outgoing, Packet, QoS};
+use tracing::error;
+
+use crate::{
+    backend::Backend,
+    point::{FieldValue, Point},
+};
+
+const QUEUE_SIZE: usize = 10_000;
+
+/// Publishes every field as its own message on `<prefix>/<measurement>/<tag values...>/<field>`,
+/// like `enphase/inverter/121234567890/last_watts`, for home automation systems. rumqttc queues
+/// messages and reconnects on its own; a background thread drives the connection.
+pub struct MqttBackend {
+    client: Client,
+    prefix: String,
+    qos: QoS,
+    retain: bool,
+    /// messages handed to rumqttc, and those the broker has acknowledged, for QoS 1 and 2
+    published: AtomicUsize,
+    acknowledged: Arc<AtomicUsize>,
+    closing: Arc<AtomicBool>,
+    connection: JoinHandle<()>,
+}
+
+impl MqttBackend {
+    /// `broker` is `host`, `host:port`, or `mqtt://host:port`.
+    pub fn connect(
+        broker: &str,
+        credentials: Option<(&str, &str)>,
+        prefix: &str,
+        qos: u8,
+        retain: bool,
+    ) -> Result<MqttBackend, EnphaseError> {
+        let address = broker.strip_prefix("mqtt://").unwrap_or(broker);
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| EnphaseError::Parse(format!("MQTT broker port in {}", broker)))?,
+            ),
+            None => (address, 1883),
+        };
+        let mut options = MqttOptions::new(
+            format!("enphase-telegraf-{}", std::process::id()),
+            host,
+            port,
+        );
+        if let Some((username, password)) = credentials {
+            options.set_credentials(username, password);
+        }
+        // room for a few scrapes' worth of fields while the broker is away
+        let (client, mut connection) = Client::new(options, QUEUE_SIZE);
+        let acknowledged = Arc::new(AtomicUsize::new(0));
+        let closing = Arc::new(AtomicBool::new(false));
+        let connection = {
+            let acknowledged = acknowledged.clone();
+            let closing = closing.clone();
+            let broker = broker.to_string();
+            thread::spawn(move || {
+                for event in connection.iter() {
+                    match event {
+                        // everything queued before the disconnect has been flushed by now
+                        Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                        Ok(Event::Incoming(Packet::PubAck(_) | Packet::PubComp(_))) => {
+                            acknowledged.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Ok(_) => {}
+                        Err(_) if closing.load(Ordering::Relaxed) => break,
+                        // the next iteration reconnects
+                        Err(e) => {
+                            error!("MQTT broker {}: {}", broker, e);
+                            thread::sleep(Duration::from_secs(5));
+                        }
+                    }
+                }
+            })
+        };
+        Ok(MqttBackend {
+            client,
+            prefix: prefix.trim_end_matches('/').to_string(),
+            qos: match qos {
+                0 => QoS::AtMostOnce,
+                1 => QoS::AtLeastOnce,
+                _ => QoS::ExactlyOnce,
+            },
+            retain,
+            published: AtomicUsize::new(0),
+            acknowledged,
+            closing,
+            connection,
+        })
+    }
+}
+
+/// `name` as one level of a topic: `/` would start another level, `+` and `#` are wildcards, and
+/// brokers don't agree on spaces.
+fn topic_level(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '+' | '#' | '\0' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+impl Backend for MqttBackend {
+    /// Publishes every message it can, logging the ones it can't; the error counts them.
+    fn write(&mut self, points: &[Point]) -> Result<(), EnphaseError> {
+        let (mut attempted, mut failed) = (0, 0);
+        for point in points {
+            let mut topic = format!("{}/{}", self.prefix, topic_level(&point.measurement));
+            for (_, value) in &point.tags {
+                topic.push('/');
+                topic.push_str(&topic_level(value));
+            }
+            for (field, value) in &point.fields {
+                let payload = match value {
+                    FieldValue::Str(s) => s.clone(),
+                    value => value.to_string(),
+                };
+                let topic = format!("{}/{}", topic, topic_level(field));
+                attempted += 1;
+                // don't block the scrape loop when the queue is full because the broker is down
+                match self
+                    .client
+                    .try_publish(topic.as_str(), self.qos, self.retain, payload)
+                {
+                    Ok(()) => {
+                        self.published.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => {
+                        error!("couldn't publish {}: {}", topic, e);
+                        failed += 1;
+                    }
+                }
+            }
+        }
+        match failed {
+            0 => Ok(()),
+            _ => Err(io::Error::other(format!(
+                "{} of {} MQTT messages weren't published",
+                failed, attempted
+            ))
+            .into()),
+        }
+    }
+
+    /// Sends whatever is still queued and disconnects. With QoS 1 or 2 this waits a little while
+    /// for the broker to acknowledge everything, since hanging up with acks in flight can make
+    /// the broker's end drop messages it hasn't read yet.
+    fn close(self: Box<Self>) {
+        if self.qos != QoS::AtMostOnce {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while self.acknowledged.load(Ordering::Relaxed) < self.published.load(Ordering::Relaxed)
+                && Instant::now() < deadline
+            {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        self.closing.store(true, Ordering::Relaxed);
+        if self.client.try_disconnect().is_ok() {
+            let _ = self.connection.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topic_levels_are_sanitized() {
+        assert_eq!(topic_level("a/b+c#d e"), "a_b_c_d_e");
+    }
+
+    #[test]
+    fn valid_topic_levels_are_kept() {
+        assert_eq!(topic_level("121234567890"), "121234567890");
+        assert_eq!(topic_level("last_watts"), "last_watts");
+    }
+}
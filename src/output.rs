@@ -0,0 +1,1503 @@
+//! Turns the Envoy's responses into [`Point`]s, in InfluxDB's data model. Each function adds the
+//! points for one endpoint.
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use enphase::{
+    clock::DateTimeDisplayResponse,
+    consumption::ConsumptionSummaryResponse,
+    ensemble::{
+        BatteryPower, DryContact, EnsembleInventoryResponse, EnsembleStatusResponse,
+        GeneratorConfig, GeneratorResponse, GridRelayResponse,
+    },
+    ev_charger::EvChargerResponse,
+    events::Event,
+    grid_profile::GridProfileResponse,
+    home::{HomeCommLevel, HomeResponse, NetworkInterface},
+    info::InfoResponse,
+    inventory::InventoryResponse,
+    inverters::InvertersResponse,
+    livedata::{LiveDataMeter, LiveDataResponse},
+    meters::{
+        ConsumptionReport, ConsumptionReportResponse, CtConfigResponse, MeterConfigResponse,
+        MeterReading, MeterReadingResponse, MetersResponse,
+    },
+    network_display::NetworkDisplayResponse,
+    newscan::NewScanResponse,
+    pdm::{InverterEnergy, PdmDevice},
+    production::{
+        EimLine, EimProduction, ProductionEntry, ProductionResponse, ProductionSummaryResponse,
+    },
+    pvlimit::PvLimitResponse,
+    stream::{MeterStreamEvent, MeterStreamPhases},
+    tariff::TariffResponse,
+    wireless::WirelessDisplayResponse,
+    EnphaseError,
+};
+use std::collections::BTreeMap;
+use tracing::warn;
+
+use crate::point::Point;
+
+/// Nanoseconds since the epoch. Only dates from 1677 to 2262 fit in an `i64`, and chrono's old
+/// `timestamp_nanos` panicked outside them; a date that far off can only be a bad clock on the
+/// Envoy, so it's reported as 0 instead.
+fn nanos<Tz: TimeZone>(time: &DateTime<Tz>) -> i64 {
+    time.timestamp_nanos_opt().unwrap_or(0)
+}
+
+/// When the scrape at `timestamp_nano` happened, for working out how old a report was then.
+fn scraped_at(timestamp_nano: u128) -> DateTime<Utc> {
+    DateTime::from_timestamp_nanos(i64::try_from(timestamp_nano).unwrap_or(i64::MAX))
+}
+
+/// The time zone the Envoy reports local times in. Its clock settings are where it's actually
+/// set, so they win over `home.json` when there are any.
+pub fn envoy_timezone(
+    home: &HomeResponse,
+    clock: Option<&DateTimeDisplayResponse>,
+) -> Result<Tz, EnphaseError> {
+    // an IANA name like `America/New_York`
+    let timezone = clock.map_or(&home.timezone, |clock| &clock.tz);
+    timezone
+        .parse()
+        .map_err(|_| EnphaseError::TimezoneNotFound(timezone.clone()))
+}
+
+/// `clock_config`: the Envoy's NTP settings, tagged with its time zone.
+pub fn clock_config_to_influx(
+    clock: &DateTimeDisplayResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    let point = Point::new("clock_config", timestamp_nano)
+        .tag("timezone", &clock.tz)
+        .field_opt("ntp_enabled", clock.ntp_enabled.map(i32::from))
+        .field_opt("ntp_server", clock.ntp_server.as_ref());
+    // a point needs a field, and cellular Envoys have none to give
+    if !point.fields.is_empty() {
+        points.push(point);
+    }
+}
+
+/// `clock` is the Envoy's clock settings, if it has them, for [`envoy_timezone`]. Without a time
+/// zone that makes sense, only `device_time_skew` is left out.
+pub fn home_to_influx(
+    home: HomeResponse,
+    clock: Option<&DateTimeDisplayResponse>,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    points.push(
+        Point::new("software_build_date", timestamp_nano)
+            .field("value", nanos(&home.software_build_epoch)),
+    );
+    points.push(
+        Point::new("database", timestamp_nano)
+            .field("total_size", home.db_size)
+            .field("percent_full", home.db_percent_full),
+    );
+    points.push(
+        Point::new("phone_home", timestamp_nano)
+            .field("update_status", home.update_status.as_str())
+            .field("nonvoy", home.is_nonvoy as i32)
+            .field("alerts", home.alerts.len())
+            .field(
+                "last_report",
+                nanos(&home.network.last_enlighten_report_time),
+            )
+            .field("web_comm", home.network.web_comm)
+            .field("ever_reported", home.network.ever_reported_to_enlighten),
+    );
+    for interface in &home.network.interfaces {
+        network_interface_to_influx(
+            interface,
+            &home.network.primary_interface,
+            timestamp_nano,
+            points,
+        );
+    }
+    for alert in &home.alerts {
+        let age = alert
+            .timestamp
+            .map(|raised| (timestamp_nano / 1_000_000_000) as i64 - raised.timestamp());
+        let mut point = Point::new("alert", timestamp_nano)
+            .tag("code", alert.code.as_deref().unwrap_or("unknown"))
+            .tag("severity", alert.severity.as_deref().unwrap_or("unknown"));
+        if let Some(serial_number) = &alert.serial_number {
+            point = point.tag("serial_number", serial_number);
+        }
+        point = point.field_opt("age_seconds", age);
+        // an alert without a code or a time isn't one this understands, so pass the whole thing on
+        if alert.code.is_none() || age.is_none() {
+            point = point.field("raw", &alert.raw);
+        }
+        points.push(point);
+    }
+    let device_time = format!("{} {}", home.current_date, home.current_time);
+    match envoy_timezone(&home, clock).map(|zone| parse_device_time(&device_time, zone)) {
+        Ok(Some(device_datetime)) => points.push(
+            Point::new("device_time_skew", timestamp_nano)
+                .field("device_timestamp", nanos(&device_datetime)),
+        ),
+        Ok(None) => warn!(
+            "leaving out device_time_skew: can't read the Envoy's time {:?}",
+            device_time
+        ),
+        Err(e) => warn!("leaving out device_time_skew: {}", e),
+    }
+    let comm = &home.comm;
+    let num = |level: Option<HomeCommLevel>| level.map_or(0, |l| l.num);
+    let level = |level: Option<HomeCommLevel>| level.map_or(0, |l| l.level);
+    points.push(
+        Point::new("comm", timestamp_nano)
+            .field("number", comm.num)
+            .field("level", comm.level)
+            .field("pcu_num", num(comm.pcu))
+            .field("pcu_level", level(comm.pcu))
+            .field("acb_num", num(comm.acb))
+            .field("acb_level", level(comm.acb))
+            .field("nsrb_num", num(comm.nsrb))
+            .field("nsrb_level", level(comm.nsrb)),
+    );
+    for (device_type, comm) in home.comm.by_device_type() {
+        points.push(
+            Point::new("comm", timestamp_nano)
+                .tag("device_type", device_type)
+                .field("number", comm.num)
+                .field("level", comm.level),
+        );
+    }
+    if let Some(wifi) = &home.wifi {
+        let mut point = Point::new("wifi_provision", timestamp_nano)
+            .field_opt("provisioned", wifi.provisioned.map(i32::from))
+            .field_opt("supported", wifi.supported.map(i32::from));
+        if let Some(status) = &wifi.status {
+            point = point.field("status", status);
+        }
+        if !point.fields.is_empty() {
+            points.push(point);
+        }
+    }
+    if let Some(enpower) = &home.enpower {
+        let mut point = Point::new("enpower", timestamp_nano);
+        if let Some(grid_status) = &enpower.grid_status {
+            point = point.tag("grid_status", grid_status);
+        }
+        // 1 when the house is on the grid; left out for anything but `open` and `closed`
+        let grid_closed = match enpower.grid_status.as_deref() {
+            Some("closed") => Some(1),
+            Some("open") => Some(0),
+            _ => None,
+        };
+        point = point
+            .field_opt("connected", enpower.connected.map(i32::from))
+            .field_opt("grid_closed", grid_closed);
+        if !point.fields.is_empty() {
+            points.push(point);
+        }
+    }
+}
+
+/// The Envoy's local time, like `01/01/2023 13:00`. Some locales use a 12-hour clock. `None` when
+/// it's in none of the formats, or in the hour skipped when clocks go forward.
+fn parse_device_time(device_time: &str, zone: Tz) -> Option<DateTime<Tz>> {
+    [
+        "%m/%d/%Y %H:%M",
+        "%m/%d/%Y %H:%M:%S",
+        "%m/%d/%Y %I:%M %p",
+        "%m/%d/%Y %I:%M:%S %p",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(device_time, format).ok())?
+    .and_local_timezone(zone)
+    // the hour after clocks go back happens twice
+    .earliest()
+}
+
+/// `wifi`: the network the Envoy is on and how well it hears it. Tagged with the SSID so roaming
+/// from one network to another shows up.
+pub fn wifi_to_influx(
+    wifi: WirelessDisplayResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    let mut point = Point::new("wifi", timestamp_nano)
+        .field_opt("channel", wifi.channel)
+        .field_opt("rssi", wifi.rssi)
+        .field_opt("security_mode", wifi.security_mode)
+        .field_opt("status", wifi.status);
+    if let Some(ssid) = wifi.ssid {
+        point = point.tag("ssid", ssid);
+    }
+    if !point.fields.is_empty() {
+        points.push(point);
+    }
+}
+
+fn network_interface_to_influx(
+    interface: &NetworkInterface,
+    primary: &str,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    points.push(
+        Point::new("network_interface", timestamp_nano)
+            .tag("interface", &interface.interface)
+            .tag("type", &interface.interface_type)
+            .field("mac", &interface.mac)
+            .field("ip", &interface.ip)
+            .field("dhcp", interface.dhcp)
+            .field("signal_strength", interface.signal_strength)
+            .field("signal_strength_max", interface.signal_strength_max)
+            .field_opt(
+                "signal_strength_percent",
+                (interface.signal_strength_max > 0).then(|| {
+                    100.0 * interface.signal_strength as f64 / interface.signal_strength_max as f64
+                }),
+            )
+            .field("carrier", interface.carrier as i32)
+            .field("primary", interface.interface == primary)
+            .field_opt("supported", interface.supported)
+            .field_opt("present", interface.present)
+            .field_opt("configured", interface.configured)
+            .field_opt("status", interface.status.as_ref()),
+    );
+}
+
+/// With `use_report_time`, each inverter's point is timestamped with its last report instead.
+/// The energy totals are matched up by serial number; an inverter in only one of the responses
+/// gets only those fields. `stale` is whether the last report is older than `stale_after`.
+/// Firmware 8's device type is a tag, and any other numbers it reports are fields named in
+/// snake_case.
+pub fn inverters_to_influx(
+    inverters: &[InvertersResponse],
+    energy: &BTreeMap<String, InverterEnergy>,
+    use_report_time: bool,
+    stale_after: chrono::Duration,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    let now = scraped_at(timestamp_nano);
+    for inverter in inverters {
+        let timestamp_nano = match u128::try_from(nanos(&inverter.last_report_date)) {
+            Ok(reported) if use_report_time => reported,
+            _ => timestamp_nano,
+        };
+        let mut point =
+            Point::new("inverter", timestamp_nano).tag("serial_number", &inverter.serial_number);
+        if let Some(dev_type) = inverter.dev_type {
+            point = point.tag("dev_type", dev_type);
+        }
+        point = point
+            .field("last_report", nanos(&inverter.last_report_date))
+            .field("last_watts", inverter.last_report_watts)
+            .field("max_watts", inverter.max_report_watts)
+            .field(
+                "stale",
+                (now - inverter.last_report_date > stale_after) as i32,
+            );
+        for (key, value) in &inverter.extra {
+            let key = snake_case(key);
+            if let Some(value) = value.as_i64() {
+                point = point.field(&key, value);
+            } else if let Some(value) = value.as_f64() {
+                point = point.field(&key, value);
+            }
+        }
+        points.push(inverter_energy_fields(
+            point,
+            energy.get(&inverter.serial_number),
+        ));
+    }
+    for (serial_number, energy) in energy {
+        if !inverters.iter().any(|i| &i.serial_number == serial_number) {
+            let point = Point::new("inverter", timestamp_nano).tag("serial_number", serial_number);
+            points.push(inverter_energy_fields(point, Some(energy)));
+        }
+    }
+}
+
+/// `lastReportVolts` to `last_report_volts`.
+fn snake_case(key: &str) -> String {
+    let mut snake = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            if !snake.is_empty() {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn inverter_energy_fields(point: Point, energy: Option<&InverterEnergy>) -> Point {
+    match energy {
+        Some(energy) => point
+            .field("today", energy.watt_hours_today)
+            .field("seven_days", energy.watt_hours_seven_days)
+            .field("lifetime", energy.watt_hours_lifetime),
+        None => point,
+    }
+}
+
+/// `<measurement>` tagged with the phase, L1 to L3, for each of the meter's lines.
+fn eim_lines_to_influx(
+    measurement: &str,
+    point: impl Fn(Point) -> Point,
+    lines: &[EimLine],
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    for (i, line) in lines.iter().enumerate() {
+        points.push(
+            point(Point::new(measurement, timestamp_nano))
+                .tag("phase", format!("L{}", i + 1))
+                .field("now", line.w_now)
+                .field("today", line.wh_today)
+                .field("seven_days", line.wh_last_seven_days)
+                .field("lifetime", line.wh_lifetime)
+                .field("current", line.rms_current)
+                .field("voltage", line.rms_voltage)
+                .field("reactive_power", line.react_pwr)
+                .field("apparent_power", line.apprnt_pwr)
+                .field("power_factor", line.pwr_factor),
+        );
+    }
+}
+
+/// `inverter_detail`, from each channel's last reading, converted to V, A, Hz, and Wh. Only
+/// devices with more than one channel are tagged with the channel.
+pub fn device_data_to_influx(
+    devices: Vec<PdmDevice>,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    for device in &devices {
+        for (i, channel) in device.channels.iter().enumerate() {
+            let mut point = Point::new("inverter_detail", timestamp_nano)
+                .tag("serial_number", &device.sn)
+                .tag("type", &device.dev_name);
+            if device.channels.len() > 1 {
+                point = point.tag("channel", i);
+            }
+            point = point
+                .field("active", device.active as i32)
+                .field_opt("power", channel.watts.as_ref().map(|w| w.now))
+                .field_opt(
+                    "lifetime_energy",
+                    channel
+                        .lifetime
+                        .as_ref()
+                        .map(|l| l.joules_produced as f64 / 3600.0),
+                );
+            if let Some(reading) = &channel.last_reading {
+                point = point
+                    .field("dc_voltage", reading.dc_voltage_mv as f64 / 1000.0)
+                    .field("dc_current", reading.dc_current_ma as f64 / 1000.0)
+                    .field("ac_voltage", reading.ac_voltage_mv as f64 / 1000.0)
+                    .field("ac_frequency", reading.ac_frequency_mhz as f64 / 1000.0)
+                    .field("temperature", reading.channel_temp)
+                    .field("last_reading", reading.end_date * 1_000_000_000);
+            }
+            points.push(point);
+        }
+    }
+}
+
+/// `fleet`, summarizing the inverters' latest reports. Nothing when there are no inverters.
+pub fn fleet_to_influx(
+    inverters: &[InvertersResponse],
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    let watts = inverters.iter().map(|inverter| inverter.last_report_watts);
+    let (Some(max_watts), Some(min_watts)) = (watts.clone().max(), watts.clone().min()) else {
+        return;
+    };
+    let total_watts: i64 = watts.map(i64::from).sum();
+    let total_max_watts: i64 = inverters
+        .iter()
+        .map(|inverter| i64::from(inverter.max_report_watts))
+        .sum();
+    let mut point = Point::new("fleet", timestamp_nano)
+        .field("count", inverters.len())
+        .field("total_watts", total_watts)
+        .field("max_watts_single", max_watts)
+        .field("min_watts_single", min_watts)
+        .field("mean_watts", total_watts as f64 / inverters.len() as f64)
+        .field("total_max_watts", total_max_watts);
+    if total_max_watts > 0 {
+        point = point.field(
+            "utilization_pct",
+            total_watts as f64 / total_max_watts as f64 * 100.0,
+        );
+    }
+    points.push(point);
+}
+
+/// `inverter_summary`: the array's output right now. `total_watts` and `max_watts` only count the
+/// `reporting_count` inverters that reported within `stale_after`, so one that has stopped doesn't
+/// keep adding its last watts; `total_count` is all of them. Nothing when there are no inverters.
+pub fn inverter_summary_to_influx(
+    inverters: &[InvertersResponse],
+    stale_after: chrono::Duration,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    if inverters.is_empty() {
+        return;
+    }
+    let now = scraped_at(timestamp_nano);
+    let reporting: Vec<&InvertersResponse> = inverters
+        .iter()
+        .filter(|inverter| now - inverter.last_report_date <= stale_after)
+        .collect();
+    let watts = reporting.iter().map(|inverter| inverter.last_report_watts);
+    points.push(
+        Point::new("inverter_summary", timestamp_nano)
+            .field("total_watts", watts.clone().map(i64::from).sum::<i64>())
+            .field_opt("max_watts", watts.max())
+            .field("reporting_count", reporting.len())
+            .field("total_count", inverters.len()),
+    );
+}
+
+/// Keeps only the inverters in `serials`, all of them when it's empty. Otherwise emits
+/// `inverter_filter` with how many were dropped, and how many of `serials` the Envoy didn't
+/// report at all.
+pub fn filter_inverters(
+    inverters: Vec<InvertersResponse>,
+    serials: &[String],
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) -> Vec<InvertersResponse> {
+    if serials.is_empty() {
+        return inverters;
+    }
+    let total = inverters.len();
+    let kept: Vec<InvertersResponse> = inverters
+        .into_iter()
+        .filter(|inverter| serials.contains(&inverter.serial_number))
+        .collect();
+    let missing = serials
+        .iter()
+        .filter(|serial| {
+            !kept
+                .iter()
+                .any(|inverter| &inverter.serial_number == *serial)
+        })
+        .count();
+    points.push(
+        Point::new("inverter_filter", timestamp_nano)
+            .field("filtered_out_count", total - kept.len())
+            .field("missing_count", missing),
+    );
+    kept
+}
+
+/// `inverter_status`, whether each inverter has reported within `stale_threshold`, and
+/// `fleet_status`, how many haven't: `offline_count` for those past `stale_threshold` and
+/// `stale_count` for those past `stale_after`.
+pub fn inverter_status_to_influx(
+    inverters: &[InvertersResponse],
+    stale_threshold: chrono::Duration,
+    stale_after: chrono::Duration,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    let now = scraped_at(timestamp_nano);
+    let (mut offline_count, mut stale_count) = (0, 0);
+    for inverter in inverters {
+        let stale = now - inverter.last_report_date;
+        let online = stale <= stale_threshold;
+        if !online {
+            offline_count += 1;
+        }
+        if stale > stale_after {
+            stale_count += 1;
+        }
+        points.push(
+            Point::new("inverter_status", timestamp_nano)
+                .tag("serial_number", &inverter.serial_number)
+                .field("online", online as i32)
+                .field("minutes_stale", stale.num_minutes().max(0)),
+        );
+    }
+    points.push(
+        Point::new("fleet_status", timestamp_nano)
+            .field("offline_count", offline_count)
+            .field("stale_count", stale_count),
+    );
+}
+
+/// The fields shared by production and consumption meter readings.
+fn eim_fields(point: Point, eim: &EimProduction) -> Point {
+    point
+        .field("now", eim.w_now)
+        .field("today", eim.wh_today)
+        .field("seven_days", eim.wh_last_seven_days)
+        .field("lifetime", eim.wh_lifetime)
+        .field("active_count", eim.active_count)
+        .field("current", eim.rms_current)
+        .field("voltage", eim.rms_voltage)
+        .field("reactive_power", eim.react_pwr)
+        .field("apparent_power", eim.apprnt_pwr)
+        .field("power_factor", eim.pwr_factor)
+}
+
+pub fn production_to_influx(
+    production: ProductionResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    for entry in &production.production {
+        match entry {
+            ProductionEntry::Inverters(inverters) => points.push(
+                Point::new("production", timestamp_nano)
+                    .tag("source", "inverters")
+                    .field("now", inverters.w_now)
+                    .field("lifetime", inverters.wh_lifetime)
+                    .field("active_count", inverters.active_count),
+            ),
+            // an unpopulated meter reports all zeros, which would look like real data
+            ProductionEntry::Eim(eim) if eim.active_count > 0 => {
+                points.push(eim_fields(
+                    Point::new("production", timestamp_nano).tag("source", "eim"),
+                    eim,
+                ));
+                eim_lines_to_influx(
+                    "production_phase",
+                    |point| point,
+                    &eim.lines,
+                    timestamp_nano,
+                    points,
+                );
+            }
+            _ => {}
+        }
+    }
+    for entry in &production.consumption {
+        if let ProductionEntry::Eim(eim) = entry {
+            if eim.active_count > 0 {
+                points.push(eim_fields(
+                    Point::new("consumption", timestamp_nano).tag("type", &eim.measurement_type),
+                    eim,
+                ));
+                eim_lines_to_influx(
+                    "consumption_phase",
+                    |point| point.tag("type", &eim.measurement_type),
+                    &eim.lines,
+                    timestamp_nano,
+                    points,
+                );
+            }
+        }
+    }
+}
+
+/// Emits `<kind>_energy` with the watt-hour totals and `<kind>_power` with the current watts.
+fn energy_summary_to_influx(
+    kind: &str,
+    summary: ProductionSummaryResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    points.push(
+        Point::new(&format!("{}_energy", kind), timestamp_nano)
+            .field("today", summary.watt_hours_today)
+            .field("seven_days", summary.watt_hours_seven_days)
+            .field("lifetime", summary.watt_hours_lifetime),
+    );
+    points.push(
+        Point::new(&format!("{}_power", kind), timestamp_nano)
+            .field("watts_now", summary.watts_now),
+    );
+}
+
+pub fn production_summary_to_influx(
+    summary: ProductionSummaryResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    energy_summary_to_influx("production", summary, timestamp_nano, points);
+}
+
+pub fn consumption_summary_to_influx(
+    summary: ConsumptionSummaryResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    energy_summary_to_influx("consumption", summary, timestamp_nano, points);
+}
+
+/// `ct_config`: which phase each CT is on and which way round it is, so a CT being moved or
+/// flipped shows up in the data it skews.
+pub fn ct_config_to_influx(
+    cts: Vec<CtConfigResponse>,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    for ct in &cts {
+        points.push(
+            Point::new("ct_config", timestamp_nano)
+                .tag("eid", ct.eid)
+                .tag("measurement_type", &ct.measurement_type)
+                .field_opt("phase", ct.phase.as_ref())
+                .field_opt("polarity", ct.polarity.as_ref())
+                .field_opt(
+                    "reversed",
+                    ct.polarity.as_ref().map(|p| (p == "reversed") as i32),
+                )
+                .field_opt("verified", ct.verified.map(i32::from)),
+        );
+    }
+}
+
+pub fn meter_config_to_influx(
+    meters: Vec<MeterConfigResponse>,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    for meter in &meters {
+        points.push(
+            Point::new("meter_config", timestamp_nano)
+                .tag("eid", meter.eid)
+                .tag("measurement_type", &meter.measurement_type)
+                .field("state", &meter.state)
+                .field("phase_mode", &meter.phase_mode)
+                .field("phase_count", meter.phase_count)
+                .field("metering_status", &meter.metering_status)
+                .field("status_flags", meter.status_flags.join(","))
+                .field("status_flag_count", meter.status_flags.len()),
+        );
+    }
+}
+
+fn meter_reading_fields(point: Point, reading: &MeterReading) -> Point {
+    point
+        .field("active_power", reading.active_power)
+        .field("reactive_power", reading.reactive_power)
+        .field("apparent_power", reading.apparent_power)
+        .field("voltage", reading.voltage)
+        .field("current", reading.current)
+        .field("frequency", reading.freq)
+        .field("power_factor", reading.pwr_factor)
+}
+
+/// `meters`: the house's production, consumption, and grid import (negative while exporting).
+pub fn meters_to_influx(meters: MetersResponse, timestamp_nano: u128, points: &mut Vec<Point>) {
+    points.push(
+        Point::new("meters", timestamp_nano)
+            .tag("measurement_type", meters.measurement_type)
+            .field("production_watts", meters.production_watts)
+            .field("consumption_watts", meters.consumption_watts)
+            .field("net_watts", meters.net_watts),
+    );
+}
+
+pub fn meter_readings_to_influx(
+    readings: Vec<MeterReadingResponse>,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    for meter in &readings {
+        points.push(meter_reading_fields(
+            Point::new("meter_reading", timestamp_nano).tag("eid", meter.eid),
+            &meter.reading,
+        ));
+        for (index, channel) in meter.channels.iter().enumerate() {
+            // phases that aren't wired read 0V; don't report them as a real reading
+            if channel.voltage == 0.0 {
+                continue;
+            }
+            points.push(meter_reading_fields(
+                Point::new("meter_channel", timestamp_nano)
+                    .tag("eid", meter.eid)
+                    .tag("channel", index)
+                    .tag("phase", format!("L{}", index + 1)),
+                channel,
+            ));
+        }
+    }
+}
+
+fn consumption_report_fields(point: Point, report: &ConsumptionReport) -> Point {
+    point
+        .field("watts", report.curr_w)
+        .field("active_power", report.act_power)
+        .field("apparent_power", report.apprnt_pwr)
+        .field("reactive_power", report.react_pwr)
+        .field("wh_delivered", report.wh_dlvd_cum)
+        .field("wh_received", report.wh_rcvd_cum)
+        .field("voltage", report.rms_voltage)
+        .field("current", report.rms_current)
+        .field("power_factor", report.pwr_factor)
+        .field("frequency", report.freq_hz)
+}
+
+pub fn consumption_reports_to_influx(
+    reports: Vec<ConsumptionReportResponse>,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    for report in &reports {
+        points.push(consumption_report_fields(
+            Point::new("consumption_report", timestamp_nano).tag("type", &report.report_type),
+            &report.cumulative,
+        ));
+        for (index, line) in report.lines.iter().enumerate() {
+            points.push(consumption_report_fields(
+                Point::new("consumption_report_line", timestamp_nano)
+                    .tag("type", &report.report_type)
+                    .tag("line", index),
+                line,
+            ));
+        }
+    }
+}
+
+pub fn inventory_to_influx(
+    inventory: Vec<InventoryResponse>,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    for group in &inventory {
+        for device in &group.devices {
+            points.push(
+                Point::new("inventory", timestamp_nano)
+                    .tag("serial", &device.serial_num)
+                    .tag("type", &group.device_type)
+                    .field("producing", device.producing as i32)
+                    .field("communicating", device.communicating as i32)
+                    .field("provisioned", device.provisioned as i32)
+                    .field("part_number", &device.part_num)
+                    .field_opt("firmware", device.img_pnum_running.as_ref())
+                    .field("installed", nanos(&device.installed))
+                    .field("last_report", nanos(&device.last_rpt_date))
+                    .field("status", device.device_status.join(","))
+                    .field("status_count", device.device_status.len())
+                    .field_opt("operating", device.operating.map(|o| o as i32)),
+            );
+        }
+    }
+}
+
+pub fn ensemble_inventory_to_influx(
+    inventory: Vec<EnsembleInventoryResponse>,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    for group in &inventory {
+        let EnsembleInventoryResponse::Encharge { devices } = group else {
+            continue;
+        };
+        for battery in devices {
+            points.push(
+                Point::new("encharge", timestamp_nano)
+                    .tag("serial", &battery.serial_num)
+                    .field("percent_full", battery.percent_full)
+                    .field("temperature", battery.temperature)
+                    .field_opt("max_cell_temperature", battery.max_cell_temp)
+                    .field("capacity", battery.encharge_capacity)
+                    .field("communicating", battery.communicating as i32)
+                    .field("operating", battery.operating as i32)
+                    .field("sleep_enabled", battery.sleep_enabled as i32)
+                    .field("dc_switch_off", battery.dc_switch_off as i32)
+                    .field_opt("led_status", battery.led_status)
+                    .field_opt("state", battery.admin_state_str.as_ref())
+                    .field("status", battery.device_status.join(","))
+                    .field("last_report", nanos(&battery.last_rpt_date)),
+            );
+        }
+    }
+}
+
+/// `battery`: each Encharge's charge, power, and temperature, from the same inventory as
+/// `encharge` and the same power readings as `battery_power`. `details` adds its size and
+/// whether it's charging.
+pub fn battery_to_influx(
+    inventory: &[EnsembleInventoryResponse],
+    power: &[BatteryPower],
+    details: bool,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    for group in inventory {
+        let EnsembleInventoryResponse::Encharge { devices } = group else {
+            continue;
+        };
+        for battery in devices {
+            let power = power.iter().find(|p| p.serial_num == battery.serial_num);
+            let point = Point::new("battery", timestamp_nano)
+                .tag("serial", &battery.serial_num)
+                .field(
+                    "soc",
+                    power.and_then(|p| p.soc).unwrap_or(battery.percent_full),
+                )
+                .field_opt("real_power_mw", power.map(|p| p.real_power_mw))
+                .field("temperature", battery.temperature);
+            if !details {
+                points.push(point);
+                continue;
+            }
+            points.push(
+                point
+                    .field("capacity_wh", battery.encharge_capacity)
+                    .field_opt("rated_power_w", battery.encharge_rated_power_w)
+                    .field("percent_full", battery.percent_full)
+                    .field_opt("charge_status", battery.charge_status.as_ref()),
+            );
+        }
+    }
+}
+
+/// Per-battery power plus the total across batteries. Negative power is discharging.
+pub fn battery_power_to_influx(
+    batteries: Vec<BatteryPower>,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    if batteries.is_empty() {
+        return;
+    }
+    for battery in &batteries {
+        points.push(
+            Point::new("battery_power", timestamp_nano)
+                .tag("serial", &battery.serial_num)
+                .field("real_power", battery.real_power_mw as f64 / 1000.0)
+                .field("apparent_power", battery.apparent_power_mva as f64 / 1000.0)
+                .field_opt("soc", battery.soc),
+        );
+    }
+    let real_mw: i64 = batteries.iter().map(|b| b.real_power_mw).sum();
+    let apparent_mva: i64 = batteries.iter().map(|b| b.apparent_power_mva).sum();
+    points.push(
+        Point::new("battery_total", timestamp_nano)
+            .field("real_power", real_mw as f64 / 1000.0)
+            .field("apparent_power", apparent_mva as f64 / 1000.0)
+            .field("count", batteries.len()),
+    );
+}
+
+pub fn ensemble_status_to_influx(
+    status: EnsembleStatusResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    points.push(
+        Point::new("ensemble_status", timestamp_nano)
+            .field("soc", status.agg_soc)
+            .field("max_energy", status.max_energy)
+            .field_opt("backup_energy", status.agg_backup_energy)
+            .field_opt("available_energy", status.agg_avail_energy)
+            .field_opt("configured_backup_soc", status.configured_backup_soc)
+            .field_opt("adjusted_backup_soc", status.adjusted_backup_soc)
+            .field_opt("encharge_soc", status.enc_agg_soc)
+            .field_opt("encharge_soh", status.enc_agg_soh)
+            .field_opt("encharge_backup_energy", status.enc_agg_backup_energy)
+            .field_opt("encharge_available_energy", status.enc_agg_avail_energy)
+            .field_opt(
+                "encharge_commissioned_capacity",
+                status.enc_commissioned_capacity,
+            )
+            .field_opt(
+                "encharge_max_available_capacity",
+                status.enc_max_available_capacity,
+            )
+            .field_opt("acb_soc", status.acb_agg_soc)
+            .field_opt("acb_energy", status.acb_agg_energy),
+    );
+}
+
+/// 1/0 for a relay or contact that's `closed`/`open`, so it can be graphed.
+fn closed(state: &str) -> Option<i32> {
+    match state {
+        "closed" => Some(1),
+        "open" => Some(0),
+        _ => None,
+    }
+}
+
+/// The relay states as given, plus 1/0 for closed/open.
+pub fn grid_relay_to_influx(
+    relay: GridRelayResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    points.push(
+        Point::new("grid_relay", timestamp_nano)
+            .field("admin_state", &relay.mains_admin_state)
+            .field_opt("admin_closed", closed(&relay.mains_admin_state))
+            .field("oper_state", &relay.mains_oper_state)
+            .field_opt("oper_closed", closed(&relay.mains_oper_state))
+            .field_opt("der1_state", relay.der1_state)
+            .field_opt("der2_state", relay.der2_state)
+            .field_opt("der3_state", relay.der3_state)
+            .field_opt("encharge_grid_mode", relay.enchg_grid_mode)
+            .field_opt("solar_grid_mode", relay.solar_grid_mode),
+    );
+}
+
+pub fn dry_contacts_to_influx(
+    contacts: Vec<DryContact>,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    for contact in &contacts {
+        let mut point = Point::new("dry_contact", timestamp_nano).tag("id", &contact.id);
+        if let Some(name) = &contact.load_name {
+            point = point.tag("name", name);
+        }
+        points.push(
+            point
+                .field("status", &contact.status)
+                .field_opt("closed", closed(&contact.status)),
+        );
+    }
+}
+
+pub fn generator_to_influx(
+    generator: GeneratorResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    let running = matches!(generator.oper_state.as_str(), "on" | "running");
+    points.push(
+        Point::new("generator", timestamp_nano)
+            .field("admin_state", &generator.admin_state)
+            .field("oper_state", &generator.oper_state)
+            .field("running", running as i32)
+            .field_opt("admin_mode", generator.admin_mode)
+            .field_opt("schedule", generator.schedule)
+            .field_opt("start_source", generator.start_source)
+            .field_opt("start_reason", generator.start_reason)
+            .field_opt("stop_reason", generator.stop_reason),
+    );
+}
+
+/// `generator_config`: the generator's rating and the battery charge it starts and stops at, so
+/// changing them shows up next to the battery's cycling.
+pub fn generator_config_to_influx(
+    config: GeneratorConfig,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    let point = Point::new("generator_config", timestamp_nano)
+        .field_opt("type", config.generator_type)
+        .field_opt("rated_power", config.rated_power)
+        .field_opt("start_soc", config.start_soc)
+        .field_opt("stop_soc", config.stop_soc);
+    if !point.fields.is_empty() {
+        points.push(point);
+    }
+}
+
+/// `live_pv`, `live_storage`, `live_grid`, and `live_load`, in W and VA.
+pub fn livedata_to_influx(
+    livedata: LiveDataResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    let meters = &livedata.meters;
+    let live = |measurement: &str, meter: &LiveDataMeter| {
+        Point::new(measurement, timestamp_nano)
+            .field("power", meter.agg_p_mw as f64 / 1000.0)
+            .field("apparent_power", meter.agg_s_mva as f64 / 1000.0)
+            .field("last_update", nanos(&meters.last_update))
+    };
+    points.push(live("live_pv", &meters.pv));
+    points.push(live("live_storage", &meters.storage).field_opt("soc", meters.soc));
+    points.push(live("live_grid", &meters.grid));
+    points.push(live("live_load", &meters.load));
+}
+
+/// `meter_stream`, tagged with the meter and phase, timestamped when the event arrived.
+pub fn meter_stream_to_influx(
+    event: MeterStreamEvent,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    let meters = [
+        ("production", Some(event.production)),
+        ("net-consumption", event.net_consumption),
+        ("total-consumption", event.total_consumption),
+    ];
+    for (meter, phases) in meters {
+        let Some(MeterStreamPhases { ph_a, ph_b, ph_c }) = phases else {
+            continue;
+        };
+        for (phase, reading) in [("a", ph_a), ("b", ph_b), ("c", ph_c)] {
+            let Some(reading) = reading else {
+                continue;
+            };
+            points.push(
+                Point::new("meter_stream", timestamp_nano)
+                    .tag("meter", meter)
+                    .tag("phase", phase)
+                    .field("power", reading.p)
+                    .field("reactive_power", reading.q)
+                    .field("apparent_power", reading.s)
+                    .field("voltage", reading.v)
+                    .field("current", reading.i)
+                    .field("power_factor", reading.pf)
+                    .field("frequency", reading.f),
+            );
+        }
+    }
+}
+
+/// `grid_profile`, with `profile_hash` so a change of profile shows up as a change of number.
+pub fn grid_profile_to_influx(
+    grid_profile: GridProfileResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    let selected = grid_profile.selected();
+    points.push(
+        Point::new("grid_profile", timestamp_nano)
+            .field("profile", &grid_profile.selected_profile)
+            .field("profile_hash", fnv1a(&grid_profile.selected_profile))
+            .field_opt("name", selected.map(|profile| &profile.name))
+            .field_opt(
+                "version",
+                selected.and_then(|profile| profile.version.as_ref()),
+            )
+            .field_opt("id", selected.and_then(|profile| profile.id.as_ref())),
+    );
+}
+
+/// 32-bit FNV-1a, which unlike std's hasher gives the same number from one build to the next.
+fn fnv1a(s: &str) -> i64 {
+    let hash = s.bytes().fold(0x811c9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+    });
+    hash.into()
+}
+
+/// `network_diag`: whether the Envoy could reach its gateway, DNS, and Enlighten, to tell its
+/// own connection going down from the collector's.
+pub fn network_display_to_influx(
+    diagnostics: NetworkDisplayResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    points.push(
+        Point::new("network_diag", timestamp_nano)
+            .field_opt("gateway", diagnostics.gateway_reachable.map(i32::from))
+            .field_opt("dns", diagnostics.dns_reachable.map(i32::from))
+            .field_opt("cloud", diagnostics.cloud_reachable.map(i32::from))
+            .field_opt("gateway_address", diagnostics.gateway.as_ref())
+            .field("dns_servers", diagnostics.dns_servers.join(",")),
+    );
+}
+
+/// `ev_charger`: what the EV charger is drawing, and how far along its session is.
+pub fn ev_charger_to_influx(
+    charger: EvChargerResponse,
+    timestamp_nano: u128,
+    points: &mut Vec<Point>,
+) {
+    let point = Point::new("ev_charger", timestamp_nano)
+        .field_opt("charging", charger.charging.map(i32::from))
+        .field_opt("power", charger.power_w)
+        .field_opt("soc_target", charger.soc_target)
+        .field_opt("session_energy", charger.session_energy_wh);
+    if !point.fields.is_empty() {
+        points.push(point);
+    }
+}
+
+/// `device_scan`: whether the Envoy is looking for new microinverters, and how many it's found.
+pub fn device_scan_to_influx(scan: NewScanResponse, timestamp_nano: u128, points: &mut Vec<Point>) {
+    let scan = scan.new_device_scan;
+    points.push(
+        Point::new("device_scan", timestamp_nano)
+            .field("active", scan.in_progress as i32)
+            .field("new_pcu_count", scan.new_pcu_count)
+            .field_opt("total_pcu_count", scan.total_pcu_count),
+    );
+}
+
+/// `pv_limit`: the export limit, and whether it's curtailing production right now.
+pub fn pv_limit_to_influx(limit: PvLimitResponse, timestamp_nano: u128, points: &mut Vec<Point>) {
+    points.push(
+        Point::new("pv_limit", timestamp_nano)
+            .field("enabled", limit.enabled as i32)
+            .field_opt("limit_watts", limit.limit_w)
+            .field("active", limit.active as i32),
+    );
+}
+
+/// `tariff`: the battery mode and reserve, and the rates if there's a single one.
+pub fn tariff_to_influx(tariff: TariffResponse, timestamp_nano: u128, points: &mut Vec<Point>) {
+    let tariff = tariff.tariff;
+    let storage = tariff.storage_settings;
+    let storage = storage.as_ref();
+    let rate = tariff.single_rate;
+    let rate = rate.as_ref();
+    let point = Point::new("tariff", timestamp_nano)
+        .field_opt("mode", storage.and_then(|s| s.mode.as_ref()))
+        .field_opt("backup_reserve", storage.and_then(|s| s.reserved_soc))
+        .field_opt("very_low_soc", storage.and_then(|s| s.very_low_soc))
+        .field_opt(
+            "charge_from_grid",
+            storage.and_then(|s| s.charge_from_grid).map(|c| c as i32),
+        )
+        .field_opt("currency", tariff.currency.and_then(|c| c.code))
+        .field_opt("rate", rate.and_then(|r| r.rate))
+        .field_opt("sell_rate", rate.and_then(|r| r.sell));
+    // a line with no fields would be rejected, along with the rest of its batch
+    if !point.fields.is_empty() {
+        points.push(point);
+    }
+}
+
+/// `event`: one point per entry in the event log, at the time it was logged. `zone` is the
+/// Envoy's, since the log's times are local and their zone abbreviations are ambiguous.
+pub fn events_to_influx(events: &[Event], zone: Tz, points: &mut Vec<Point>) {
+    for event in events {
+        // like `Thu Jun 29, 2023 10:34 AM PDT`
+        let date = NaiveDateTime::parse_and_remainder(&event.date, "%a %b %d, %Y %I:%M %p")
+            .ok()
+            .and_then(|(date, _)| date.and_local_timezone(zone).earliest());
+        let Some(date) = date else {
+            warn!(
+                "skipping event {}: can't read its date {:?}",
+                event.id, event.date
+            );
+            continue;
+        };
+        let Ok(timestamp_nano) = u128::try_from(nanos(&date)) else {
+            continue;
+        };
+        points.push(
+            Point::new("event", timestamp_nano)
+                .tag("serial_number", event.serial())
+                .tag("event", &event.description)
+                .field("id", event.id),
+        );
+    }
+}
+
+/// `envoy_up`: whether the Envoy could be scraped, so a gap in the data can be told apart from
+/// the panels being off.
+pub fn envoy_up_to_influx(up: bool, timestamp_nano: u128, points: &mut Vec<Point>) {
+    points.push(Point::new("envoy_up", timestamp_nano).field("value", up as i32));
+}
+
+pub fn info_to_influx(info: &InfoResponse, timestamp_nano: u128, points: &mut Vec<Point>) {
+    points.push(
+        Point::new("envoy_info", timestamp_nano)
+            .field("software", &info.device.software)
+            .field("part_number", &info.device.part_number)
+            .field("imeter", info.device.imeter)
+            .field("web_tokens", info.web_tokens),
+    );
+    for package in &info.packages {
+        points.push(
+            Point::new("envoy_package", timestamp_nano)
+                .tag("package", &package.name)
+                .field("version", &package.version)
+                .field("build", &package.build),
+        );
+    }
+}
+
+/// `firmware`: what the Envoy is running, and whether it has meters.
+pub fn firmware_to_influx(info: &InfoResponse, timestamp_nano: u128, points: &mut Vec<Point>) {
+    // the app package's build is the one that goes with `software`
+    let build = info
+        .packages
+        .iter()
+        .find(|p| p.name == "app")
+        .or(info.packages.first());
+    points.push(
+        Point::new("firmware", timestamp_nano)
+            .field("software_version", &info.device.software)
+            .field_opt("build_id", build.map(|p| &p.build))
+            .field("is_metered", info.device.imeter),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// home.json from firmware 7, with `change` applied.
+    fn home(change: impl FnOnce(&mut serde_json::Value)) -> HomeResponse {
+        let mut home = serde_json::json!({
+            "software_build_epoch": 1234567890,
+            "is_nonvoy": false,
+            "db_size": "12 MB",
+            "db_percent_full": "1",
+            "timezone": "America/New_York",
+            "current_date": "01/01/2023",
+            "current_time": "01:00",
+            "network": {
+                "web_comm": true,
+                "ever_reported_to_enlighten": true,
+                "last_enlighten_report_time": 1234567890,
+                "primary_interface": "eth0",
+                "interfaces": []
+            },
+            "comm": {"num": 5, "level": 4},
+            "alerts": [],
+            "update_status": "satisfied"
+        });
+        change(&mut home);
+        serde_json::from_value(home).unwrap()
+    }
+
+    fn comm_point(home: HomeResponse) -> String {
+        let mut points = Vec::new();
+        home_to_influx(home, None, 1, &mut points);
+        let comm = points.iter().find(|p| p.measurement == "comm").unwrap();
+        comm.to_string()
+    }
+
+    #[test]
+    fn unknown_timezone_only_loses_device_time_skew() {
+        let mut points = Vec::new();
+        home_to_influx(home(|_| {}), None, 1, &mut points);
+        let measurements: Vec<_> = points.iter().map(|p| p.measurement.clone()).collect();
+        assert!(measurements.contains(&"device_time_skew".to_string()));
+
+        let mut bad_points = Vec::new();
+        let home = home(|home| home["timezone"] = "Mars/Olympus_Mons".into());
+        home_to_influx(home, None, 1, &mut bad_points);
+        let bad_measurements: Vec<_> = bad_points.iter().map(|p| p.measurement.clone()).collect();
+        let expected: Vec<_> = measurements
+            .into_iter()
+            .filter(|m| m != "device_time_skew")
+            .collect();
+        assert_eq!(bad_measurements, expected);
+    }
+
+    #[test]
+    fn comm_by_device_type() {
+        let home = home(|home| {
+            home["comm"]["pcu"] = serde_json::json!({"num": 4, "level": 3});
+            home["comm"]["acb"] = serde_json::json!({"num": 1, "level": 5});
+        });
+        assert_eq!(
+            comm_point(home),
+            "comm number=5,level=4,pcu_num=4,pcu_level=3,acb_num=1,acb_level=5,nsrb_num=0,\
+             nsrb_level=0 1"
+        );
+    }
+
+    #[test]
+    fn comm_without_device_types_is_zero() {
+        assert_eq!(
+            comm_point(home(|_| {})),
+            "comm number=5,level=4,pcu_num=0,pcu_level=0,acb_num=0,acb_level=0,nsrb_num=0,\
+             nsrb_level=0 1"
+        );
+    }
+
+    #[test]
+    fn firmware_fields() {
+        use enphase::info::{InfoDevice, InfoPackage};
+
+        let package = |name: &str, build: &str| InfoPackage {
+            name: name.to_string(),
+            version: "1".to_string(),
+            build: build.to_string(),
+        };
+        let info = InfoResponse {
+            time: 1688000000,
+            device: InfoDevice {
+                serial_number: "122012345678".to_string(),
+                part_number: "800-00555-r03".to_string(),
+                software: "D7.6.175".to_string(),
+                imeter: true,
+            },
+            web_tokens: true,
+            packages: vec![package("rootfs", "950"), package("app", "release-7.6.175")],
+        };
+        let mut points = Vec::new();
+        firmware_to_influx(&info, 1, &mut points);
+        assert_eq!(
+            points[0].to_string(),
+            "firmware software_version=\"D7.6.175\",build_id=\"release-7.6.175\",is_metered=true 1"
+        );
+    }
+
+    #[test]
+    fn meters_fields() {
+        let meters = MetersResponse {
+            production_watts: 3000.0,
+            consumption_watts: 1200.5,
+            net_watts: -1799.5,
+            measurement_type: "net-consumption".to_string(),
+        };
+        let mut points = Vec::new();
+        meters_to_influx(meters, 1, &mut points);
+        assert_eq!(
+            points[0].to_string(),
+            "meters,measurement_type=net-consumption production_watts=3000,\
+             consumption_watts=1200.5,net_watts=-1799.5 1"
+        );
+    }
+
+    #[test]
+    fn firmware_8_inverter_fields() {
+        let inverters: Vec<InvertersResponse> =
+            serde_json::from_str(include_str!("../enphase/fixtures/inverters_d8.json")).unwrap();
+        let mut points = Vec::new();
+        inverters_to_influx(
+            &inverters,
+            &BTreeMap::new(),
+            false,
+            chrono::Duration::MAX,
+            1,
+            &mut points,
+        );
+        assert_eq!(
+            points[0].to_string(),
+            "inverter,serial_number=482312345678,dev_type=1 last_report=1718000000000000000,\
+             last_watts=281,max_watts=295,stale=0,last_report_volts=240.5,report_count=3 1"
+        );
+    }
+
+    #[test]
+    fn firmware_7_inverter_fields() {
+        let inverters: Vec<InvertersResponse> = serde_json::from_str(
+            r#"[{"serialNumber": "123456789012", "lastReportDate": 1688000000, "lastReportWatts": 123, "maxReportWatts": 234}]"#,
+        )
+        .unwrap();
+        let mut points = Vec::new();
+        inverters_to_influx(
+            &inverters,
+            &BTreeMap::new(),
+            false,
+            chrono::Duration::MAX,
+            1,
+            &mut points,
+        );
+        assert_eq!(
+            points[0].to_string(),
+            "inverter,serial_number=123456789012 last_report=1688000000000000000,\
+             last_watts=123,max_watts=234,stale=0 1"
+        );
+    }
+
+    #[test]
+    fn inverter_summary_leaves_out_stale_inverters() {
+        let inverters: Vec<InvertersResponse> = serde_json::from_str(
+            r#"[
+                {"serialNumber": "1", "lastReportDate": 1688000000, "lastReportWatts": 200, "maxReportWatts": 295},
+                {"serialNumber": "2", "lastReportDate": 1687999900, "lastReportWatts": 250, "maxReportWatts": 295},
+                {"serialNumber": "3", "lastReportDate": 1687999000, "lastReportWatts": 280, "maxReportWatts": 295}
+            ]"#,
+        )
+        .unwrap();
+        // scraped ten minutes after the first report
+        let scraped = 1_688_000_600_000_000_000;
+        let mut points = Vec::new();
+        inverter_summary_to_influx(
+            &inverters,
+            chrono::Duration::minutes(15),
+            scraped,
+            &mut points,
+        );
+        assert_eq!(
+            points[0].to_string(),
+            format!(
+                "inverter_summary total_watts=450,max_watts=250,reporting_count=2,\
+                 total_count=3 {}",
+                scraped
+            )
+        );
+    }
+
+    #[test]
+    fn inverter_status_is_as_of_the_scrape() {
+        let inverters: Vec<InvertersResponse> = serde_json::from_str(
+            r#"[
+                {"serialNumber": "1", "lastReportDate": 1688000000, "lastReportWatts": 200, "maxReportWatts": 295},
+                {"serialNumber": "2", "lastReportDate": 1687998000, "lastReportWatts": 0, "maxReportWatts": 295}
+            ]"#,
+        )
+        .unwrap();
+        // five minutes after the first report, and 38 after the second's
+        let scraped = 1_688_000_300_000_000_000;
+        let mut points = Vec::new();
+        inverter_status_to_influx(
+            &inverters,
+            chrono::Duration::minutes(20),
+            chrono::Duration::minutes(15),
+            scraped,
+            &mut points,
+        );
+        let lines: Vec<String> = points.iter().map(Point::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                format!(
+                    "inverter_status,serial_number=1 online=1,minutes_stale=5 {}",
+                    scraped
+                ),
+                format!(
+                    "inverter_status,serial_number=2 online=0,minutes_stale=38 {}",
+                    scraped
+                ),
+                format!("fleet_status offline_count=1,stale_count=1 {}", scraped),
+            ]
+        );
+    }
+
+    #[test]
+    fn battery_fields() {
+        let inventory: Vec<EnsembleInventoryResponse> = serde_json::from_str(
+            r#"[
+                {"type": "ENCHARGE", "devices": [{
+                    "part_num": "830-01760-r37", "serial_num": "122100000000",
+                    "device_status": ["envoy.global.ok"], "last_rpt_date": 1688000000,
+                    "percentFull": 95, "temperature": 29, "encharge_capacity": 3500,
+                    "encharge_rated_power_w": 3840, "charge_status": "charging"
+                }]},
+                {"type": "ENPOWER", "devices": []}
+            ]"#,
+        )
+        .unwrap();
+        let power: Vec<BatteryPower> = serde_json::from_str(
+            r#"[{"serial_num": "122100000000", "real_power_mw": -1250000, "apparent_power_mva": 1300000, "soc": 94}]"#,
+        )
+        .unwrap();
+        let mut points = Vec::new();
+        battery_to_influx(&inventory, &power, false, 1, &mut points);
+        assert_eq!(points.len(), 1);
+        assert_eq!(
+            points[0].to_string(),
+            "battery,serial=122100000000 soc=94,real_power_mw=-1250000,temperature=29 1"
+        );
+
+        let mut points = Vec::new();
+        battery_to_influx(&inventory, &[], true, 1, &mut points);
+        assert_eq!(
+            points[0].to_string(),
+            "battery,serial=122100000000 soc=95,temperature=29,capacity_wh=3500,\
+             rated_power_w=3840,percent_full=95,charge_status=\"charging\" 1"
+        );
+    }
+
+    #[test]
+    fn empty_tariff_is_left_out() {
+        let tariff: TariffResponse = serde_json::from_str(r#"{"tariff": {}}"#).unwrap();
+        let mut points = Vec::new();
+        tariff_to_influx(tariff, 1, &mut points);
+        assert!(points.is_empty());
+    }
+
+    #[test]
+    fn snake_case_field_names() {
+        assert_eq!(snake_case("lastReportVolts"), "last_report_volts");
+        assert_eq!(snake_case("acFrequency"), "ac_frequency");
+        assert_eq!(snake_case("temperature"), "temperature");
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt;
+
+/// One measurement, in InfluxDB's data model: a name, some tags identifying what was measured, and
+/// the measured fields. Every output format is rendered from these.
+pub struct Point {
+    pub measurement: String,
+    pub tags: Vec<(String, String)>,
+    pub fields: Vec<(String, FieldValue)>,
+    pub timestamp_nano: u128,
+}
+
+pub enum FieldValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl Point {
+    pub fn new(measurement: &str, timestamp_nano: u128) -> Point {
+        Point {
+            measurement: measurement.to_string(),
+            tags: Vec::new(),
+            fields: Vec::new(),
+            timestamp_nano,
+        }
+    }
+
+    pub fn tag(mut self, key: &str, value: impl fmt::Display) -> Point {
+        self.tags.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn field(mut self, key: &str, value: impl Into<FieldValue>) -> Point {
+        self.fields.push((key.to_string(), value.into()));
+        self
+    }
+
+    /// Adds the field only if there's a value for it.
+    pub fn field_opt(self, key: &str, value: Option<impl Into<FieldValue>>) -> Point {
+        match value {
+            Some(value) => self.field(key, value),
+            None => self,
+        }
+    }
+}
+
+/// InfluxDB line protocol
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", escape(&self.measurement, &[',', ' ']))?;
+        for (key, value) in &self.tags {
+            write!(f, ",{}={}", escape_tag(key), escape_tag(value))?;
+        }
+        for (i, (key, value)) in self.fields.iter().enumerate() {
+            let separator = if i == 0 { ' ' } else { ',' };
+            write!(f, "{}{}={}", separator, escape_tag(key), value)?;
+        }
+        write!(f, " {}", self.timestamp_nano)
+    }
+}
+
+/// For tag keys, tag values, and field keys.
+fn escape_tag(s: &str) -> String {
+    escape(s, &[',', '=', ' '])
+}
+
+/// For string field values, which are quoted.
+fn escape_field(s: &str) -> String {
+    escape(s, &['"', '\\'])
+}
+
+/// Backslashes the characters that would otherwise end this part of the line.
+fn escape(s: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Int(i) => write!(f, "{}", i),
+            FieldValue::Float(x) => write!(f, "{}", x),
+            FieldValue::Bool(b) => write!(f, "{}", b),
+            FieldValue::Str(s) => write!(f, "\"{}\"", escape_field(s)),
+        }
+    }
+}
+
+impl From<i32> for FieldValue {
+    fn from(i: i32) -> Self {
+        FieldValue::Int(i.into())
+    }
+}
+
+impl From<i64> for FieldValue {
+    fn from(i: i64) -> Self {
+        FieldValue::Int(i)
+    }
+}
+
+impl From<usize> for FieldValue {
+    fn from(i: usize) -> Self {
+        FieldValue::Int(i as i64)
+    }
+}
+
+impl From<f64> for FieldValue {
+    fn from(x: f64) -> Self {
+        FieldValue::Float(x)
+    }
+}
+
+impl From<bool> for FieldValue {
+    fn from(b: bool) -> Self {
+        FieldValue::Bool(b)
+    }
+}
+
+impl From<String> for FieldValue {
+    fn from(s: String) -> Self {
+        FieldValue::Str(s)
+    }
+}
+
+impl From<&str> for FieldValue {
+    fn from(s: &str) -> Self {
+        FieldValue::Str(s.to_string())
+    }
+}
+
+impl From<&String> for FieldValue {
+    fn from(s: &String) -> Self {
+        FieldValue::Str(s.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_fields_are_quoted_and_escaped() {
+        let point = Point::new("phone_home", 1).field("update_status", r#"say "hi", \ bye"#);
+        assert_eq!(
+            point.to_string(),
+            r#"phone_home update_status="say \"hi\", \\ bye" 1"#
+        );
+    }
+
+    #[test]
+    fn tags_are_escaped_and_unquoted() {
+        let point = Point::new("inverter", 1)
+            .tag("serial_number", "123456789012")
+            .tag("site", "my house,east=1")
+            .field("last_watts", 250);
+        assert_eq!(
+            point.to_string(),
+            r"inverter,serial_number=123456789012,site=my\ house\,east\=1 last_watts=250 1"
+        );
+    }
+
+    #[test]
+    fn measurement_and_field_keys_are_escaped() {
+        let point = Point::new("solar site,1", 1).field("last watts", 250);
+        assert_eq!(point.to_string(), r"solar\ site\,1 last\ watts=250 1");
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use crate::point::{FieldValue, Point};
+
+/// Renders points in the Prometheus text exposition format, suitable for the node_exporter
+/// textfile collector or a pushgateway. Each numeric field becomes a gauge named
+/// `enphase_<measurement>_<field>` labelled with the point's tags. Prometheus has no string
+/// values, so string fields become an `_info` gauge of 1 with the string in a `value` label.
+/// Timestamps are left off since the textfile collector rejects them. Inverters follow the
+/// Envoy's names, as `enphase_inverter_last_report_watts{serial="..."}`.
+pub fn to_prometheus(points: &[Point]) -> String {
+    // metric families in the order they're first seen, each with its samples
+    let mut families: Vec<(String, String, Vec<String>)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for point in points {
+        for (field, value) in &point.fields {
+            let mut labels: Vec<(String, String)> = point
+                .tags
+                .iter()
+                .map(|(key, value)| (label_name(key).to_string(), value.clone()))
+                .collect();
+            let (name, sample) = match value {
+                FieldValue::Int(i) => (metric_name(point, field), *i as f64),
+                FieldValue::Float(x) => (metric_name(point, field), *x),
+                FieldValue::Bool(b) => (metric_name(point, field), if *b { 1.0 } else { 0.0 }),
+                FieldValue::Str(s) => {
+                    labels.push(("value".to_string(), s.clone()));
+                    (format!("{}_info", metric_name(point, field)), 1.0)
+                }
+            };
+            let family = *index.entry(name.clone()).or_insert_with(|| {
+                let help = format!(
+                    "The {} field of the {} measurement",
+                    field, point.measurement
+                );
+                families.push((name.clone(), help, Vec::new()));
+                families.len() - 1
+            });
+            families[family].2.push(format!(
+                "{}{} {}",
+                name,
+                format_labels(&labels),
+                format_value(sample)
+            ));
+        }
+    }
+
+    let mut out = String::new();
+    for (name, help, samples) in families {
+        out.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} gauge\n",
+            name, help, name
+        ));
+        for sample in samples {
+            out.push_str(&sample);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn metric_name(point: &Point, field: &str) -> String {
+    // `ends_with`, since `--measurement-prefix` may be in front
+    let field = match field {
+        "last_watts" if point.measurement.ends_with("inverter") => "last_report_watts",
+        "max_watts" if point.measurement.ends_with("inverter") => "max_report_watts",
+        field => field,
+    };
+    sanitize(&format!("enphase_{}_{}", point.measurement, field), true)
+}
+
+fn label_name(tag: &str) -> &str {
+    match tag {
+        "serial_number" => "serial",
+        tag => tag,
+    }
+}
+
+/// Prometheus names are ASCII letters, digits, and underscores, plus colons in metric names, and
+/// can't start with a digit. Anything else, like the `-` in `--measurement-prefix solar-` or in a
+/// `--tag` key, becomes `_`.
+fn sanitize(name: &str, colons: bool) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => c,
+            ':' if colons => c,
+            _ => '_',
+        })
+        .collect();
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+fn format_labels(labels: &[(String, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", sanitize(key, false), escape_label_value(value)))
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_sanitized() {
+        let point = Point::new("solar-inverter", 1)
+            .tag("my-site", "east-1")
+            .tag("2nd.tag", "x")
+            .field("lastReport.volts", 240.5);
+        assert_eq!(
+            to_prometheus(&[point]),
+            "# HELP enphase_solar_inverter_lastReport_volts The lastReport.volts field of the \
+             solar-inverter measurement\n\
+             # TYPE enphase_solar_inverter_lastReport_volts gauge\n\
+             enphase_solar_inverter_lastReport_volts{my_site=\"east-1\",_2nd_tag=\"x\"} 240.5\n"
+        );
+    }
+
+    #[test]
+    fn valid_names_are_kept() {
+        assert_eq!(sanitize("enphase_a:b_1", true), "enphase_a:b_1");
+        assert_eq!(sanitize("a:b", false), "a_b");
+        let point = Point::new("inverter", 1)
+            .tag("serial_number", "123456789012")
+            .field("last_watts", 123)
+            .field("max_watts", 234)
+            .field("state", "producing");
+        assert_eq!(
+            to_prometheus(&[point]),
+            "# HELP enphase_inverter_last_report_watts The last_watts field of the inverter \
+             measurement\n\
+             # TYPE enphase_inverter_last_report_watts gauge\n\
+             enphase_inverter_last_report_watts{serial=\"123456789012\"} 123\n\
+             # HELP enphase_inverter_max_report_watts The max_watts field of the inverter \
+             measurement\n\
+             # TYPE enphase_inverter_max_report_watts gauge\n\
+             enphase_inverter_max_report_watts{serial=\"123456789012\"} 234\n\
+             # HELP enphase_inverter_state_info The state field of the inverter measurement\n\
+             # TYPE enphase_inverter_state_info gauge\n\
+             enphase_inverter_state_info{serial=\"123456789012\",value=\"producing\"} 1\n"
+        );
+    }
+}
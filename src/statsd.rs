@@ -0,0 +1,125 @@
+use std::net::UdpSocket;
+
+use clap::ValueEnum;
+use enphase::EnphaseError;
+use serde::Deserialize;
+
+use crate::{
+    backend::Backend,
+    point::{FieldValue, Point},
+};
+
+#[derive(Clone, Copy, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StatsdFormat {
+    /// Tags after the name, as `name,tag=value:1|g`, which telegraf's statsd input understands
+    #[default]
+    Statsd,
+    /// Datadog's tags, as `name:1|g|#tag:value`
+    Dogstatsd,
+}
+
+/// Sends every numeric field as a gauge named `<prefix>.<measurement>.<field>`, tagged with the
+/// point's tags. Like any StatsD client, it doesn't care whether anything is listening: send
+/// errors are ignored.
+pub struct StatsdBackend {
+    socket: UdpSocket,
+    prefix: String,
+    format: StatsdFormat,
+}
+
+impl StatsdBackend {
+    /// `host` is `HOST:PORT`.
+    pub fn connect(
+        host: &str,
+        prefix: &str,
+        format: StatsdFormat,
+    ) -> Result<StatsdBackend, EnphaseError> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.connect(host)?;
+        Ok(StatsdBackend {
+            socket,
+            prefix: prefix.trim_end_matches('.').to_string(),
+            format,
+        })
+    }
+
+    fn metric(&self, name: &str, value: &str, tags: &[(String, String)]) -> String {
+        match self.format {
+            StatsdFormat::Statsd => {
+                let mut metric = name.to_string();
+                for (key, tag) in tags {
+                    metric.push_str(&format!(",{}={}", sanitize(key), sanitize(tag)));
+                }
+                format!("{}:{}|g", metric, value)
+            }
+            StatsdFormat::Dogstatsd if tags.is_empty() => format!("{}:{}|g", name, value),
+            StatsdFormat::Dogstatsd => {
+                let tags: Vec<String> = tags
+                    .iter()
+                    .map(|(key, tag)| format!("{}:{}", sanitize(key), sanitize(tag)))
+                    .collect();
+                format!("{}:{}|g|#{}", name, value, tags.join(","))
+            }
+        }
+    }
+}
+
+/// `name` with the characters that separate a datagram's parts replaced by `_`.
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            ':' | '|' | ',' | '#' | '=' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+impl Backend for StatsdBackend {
+    fn write(&mut self, points: &[Point]) -> Result<(), EnphaseError> {
+        for point in points {
+            for (field, value) in &point.fields {
+                let value = match value {
+                    FieldValue::Int(i) => i.to_string(),
+                    FieldValue::Float(x) => x.to_string(),
+                    FieldValue::Bool(b) => (*b as i32).to_string(),
+                    FieldValue::Str(_) => continue,
+                };
+                let name = format!("{}.{}.{}", self.prefix, point.measurement, field);
+                let _ = self
+                    .socket
+                    .send(self.metric(&name, &value, &point.tags).as_bytes());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend(format: StatsdFormat) -> StatsdBackend {
+        StatsdBackend::connect("127.0.0.1:8125", "enphase", format).unwrap()
+    }
+
+    #[test]
+    fn tags_are_sanitized() {
+        let tags = [("site:name".to_string(), "a|b,c#d=e f".to_string())];
+        assert_eq!(
+            backend(StatsdFormat::Statsd).metric("enphase.inverter.last_watts", "1", &tags),
+            "enphase.inverter.last_watts,site_name=a_b_c_d_e_f:1|g"
+        );
+        assert_eq!(
+            backend(StatsdFormat::Dogstatsd).metric("enphase.inverter.last_watts", "1", &tags),
+            "enphase.inverter.last_watts:1|g|#site_name:a_b_c_d_e_f"
+        );
+    }
+
+    #[test]
+    fn valid_tags_are_kept() {
+        assert_eq!(sanitize("121234567890"), "121234567890");
+        assert_eq!(sanitize("serial_number"), "serial_number");
+    }
+}